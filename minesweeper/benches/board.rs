@@ -0,0 +1,146 @@
+use minesweeper::{
+  minesweeper::{Minesweeper, Position},
+  random::{RandomSource, SplitMix64},
+};
+use std::{
+  collections::HashSet,
+  hint::black_box,
+  time::{Duration, Instant},
+};
+
+// Run with `cargo bench`. Prints the average time per iteration of common
+// operations on huge boards. Where it makes sense, the same operation is also
+// run on hash sets of positions, which is how boards used to be stored.
+
+const SIZE: usize = 1000;
+const MINES: usize = SIZE * SIZE / 5;
+const CENTER: Position = (SIZE / 2, SIZE / 2);
+
+fn measure<T>(iterations: u32, mut f: impl FnMut() -> T) -> Duration {
+  black_box(f());
+
+  let start = Instant::now();
+
+  for _ in 0..iterations {
+    black_box(f());
+  }
+
+  start.elapsed() / iterations
+}
+
+fn bench<T>(name: &str, iterations: u32, f: impl FnMut() -> T) {
+  println!("{:<24} {:>12.3?}", name, measure(iterations, f));
+}
+
+fn compare<T, U>(
+  name: &str,
+  iterations: u32,
+  f: impl FnMut() -> T,
+  baseline: impl FnMut() -> U,
+) {
+  println!(
+    "{:<24} {:>12.3?} {:>12.3?}",
+    name,
+    measure(iterations, f),
+    measure(iterations, baseline)
+  );
+}
+
+// The fields of a game as hash sets
+struct HashBoard {
+  mines: HashSet<Position>,
+  open: HashSet<Position>,
+  flagged: HashSet<Position>,
+}
+
+impl HashBoard {
+  fn new(ms: &Minesweeper, mines: &[Position]) -> HashBoard {
+    HashBoard {
+      mines: mines.iter().copied().collect(),
+      open: ms.iter_positions().filter(|&pos| ms.is_open(pos)).collect(),
+      flagged: ms
+        .iter_positions()
+        .filter(|&pos| ms.is_flagged(pos))
+        .collect(),
+    }
+  }
+}
+
+// Random mines that keep the center free, so opening it clears an area
+fn mines() -> Vec<Position> {
+  let mut rng = SplitMix64::new(42);
+  let mut mines = HashSet::new();
+
+  while mines.len() < MINES {
+    let pos = (rng.range(0, SIZE), rng.range(0, SIZE));
+
+    if pos.0.abs_diff(CENTER.0) > 1 || pos.1.abs_diff(CENTER.1) > 1 {
+      mines.insert(pos);
+    }
+  }
+
+  mines.into_iter().collect()
+}
+
+fn game() -> Minesweeper {
+  let mut ms = Minesweeper::with_seed(SIZE, SIZE, MINES, 42);
+  ms.open(CENTER);
+  ms
+}
+
+fn main() {
+  println!("{}x{} board with {} mines\n", SIZE, SIZE, MINES);
+  println!("{:<24} {:>12} {:>12}", "", "bitset", "HashSet");
+
+  bench("new game + first open", 5, game);
+
+  let mines = mines();
+  let mut ms = Minesweeper::with_mines(SIZE, SIZE, mines.iter().copied());
+  ms.open(CENTER);
+
+  let reference = HashBoard::new(&ms, &mines);
+
+  compare(
+    "neighboring_mines",
+    5,
+    || {
+      ms.iter_positions()
+        .map(|pos| ms.neighboring_mines(pos) as usize)
+        .sum::<usize>()
+    },
+    || {
+      ms.iter_positions()
+        .map(|pos| {
+          ms.iter_neighbors(pos)
+            .filter(|neighbor| reference.mines.contains(neighbor))
+            .count()
+        })
+        .sum::<usize>()
+    },
+  );
+  compare(
+    "is_open + is_flagged",
+    5,
+    || {
+      ms.iter_positions()
+        .filter(|&pos| ms.is_open(pos) || ms.is_flagged(pos))
+        .count()
+    },
+    || {
+      ms.iter_positions()
+        .filter(|pos| {
+          reference.open.contains(pos) || reference.flagged.contains(pos)
+        })
+        .count()
+    },
+  );
+  bench("snapshot", 5, || ms.snapshot());
+  bench("display", 2, || ms.to_string());
+
+  // Also records history, so there is no comparable hash set operation
+  bench("toggle_flag row", 5, || {
+    for x in 0..SIZE {
+      ms.toggle_flag((x, 0));
+    }
+  });
+}
@@ -0,0 +1,104 @@
+use minesweeper::{
+  bot::{Bot, BotResult, GuessPolicy},
+  minesweeper::{Difficulty, FirstClick, GameOptions, Generation, Minesweeper},
+};
+use std::{env, process, time::Instant};
+
+// Lets the bot play seeded games with different generator settings, to compare
+// how they affect win rates and guessing:
+//
+//   cargo run --release --bin bot -- [games per setting] [safest|random]
+
+const USAGE: &str = "usage: bot [games per setting] [safest|random]";
+
+const DIFFICULTIES: [(&str, Difficulty); 3] = [
+  ("beginner", Difficulty::Beginner),
+  ("intermediate", Difficulty::Intermediate),
+  ("expert", Difficulty::Expert),
+];
+
+const GENERATORS: [(&str, FirstClick, Generation); 4] = [
+  ("unprotected", FirstClick::Unprotected, Generation::Random),
+  ("safe field", FirstClick::SafeField, Generation::Random),
+  (
+    "neighborhood",
+    FirstClick::SafeNeighborhood,
+    Generation::Random,
+  ),
+  (
+    "no guess",
+    FirstClick::SafeNeighborhood,
+    Generation::NoGuess { max_attempts: 1000 },
+  ),
+];
+
+fn parse_args(args: &[String]) -> Option<(u64, GuessPolicy)> {
+  let games = match args.first() {
+    Some(games) => games.parse().ok().filter(|&games| games > 0)?,
+    None => 100,
+  };
+  let policy = match args.get(1).map(|policy| &policy[..]) {
+    None | Some("safest") => GuessPolicy::SafestField,
+    Some("random") => GuessPolicy::Random,
+    _ => return None,
+  };
+
+  (args.len() <= 2).then_some((games, policy))
+}
+
+fn main() {
+  let args = env::args().skip(1).collect::<Vec<_>>();
+  let (games, policy) = parse_args(&args).unwrap_or_else(|| {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+  });
+
+  println!("{} games per setting, {:?} guessing\n", games, policy);
+  println!(
+    "{:<14}{:<14}{:>10}{:>10}{:>12}{:>10}",
+    "difficulty", "generator", "win rate", "guesses", "3BV/move", "ms/game"
+  );
+
+  for (difficulty_name, difficulty) in DIFFICULTIES {
+    for (generator_name, first_click, generation) in GENERATORS {
+      let start = Instant::now();
+      let results = (0..games)
+        .map(|seed| {
+          let options = GameOptions {
+            seed: Some(seed),
+            first_click,
+            generation,
+            ..GameOptions::default()
+          };
+          let mut ms = Minesweeper::with_difficulty(difficulty, options);
+
+          Bot::new(policy, seed).play(&mut ms)
+        })
+        .collect::<Vec<BotResult>>();
+      let elapsed = start.elapsed();
+
+      let count = results.len() as f64;
+      let wins = results.iter().filter(|result| result.won).count();
+      let guesses = results.iter().map(|result| result.guesses).sum::<usize>();
+      let efficiency = results
+        .iter()
+        .filter(|result| result.won)
+        .map(|result| result.efficiency())
+        .sum::<f64>();
+
+      println!(
+        "{:<14}{:<14}{:>9.1}%{:>10.2}{:>12.3}{:>10.2}",
+        difficulty_name,
+        generator_name,
+        wins as f64 / count * 100.0,
+        guesses as f64 / count,
+        if wins > 0 {
+          efficiency / wins as f64
+        } else {
+          0.0
+        },
+        elapsed.as_secs_f64() * 1000.0 / count,
+      );
+    }
+  }
+}
@@ -0,0 +1,217 @@
+use crossterm::{
+  cursor::{Hide, MoveTo, Show},
+  event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+  execute, queue,
+  style::{Print, Stylize},
+  terminal::{
+    disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
+    LeaveAlternateScreen,
+  },
+};
+use minesweeper::{
+  minesweeper::{
+    Difficulty, FirstClick, GameOptions, GameStatus, Minesweeper,
+    MinesweeperError,
+  },
+  render::{Ansi, Ascii},
+  snapshot::FieldState,
+};
+use std::{
+  env,
+  io::{self, Write},
+  process,
+  time::Duration,
+};
+
+// Plays a game in the terminal:
+//
+//   cargo run --bin terminal -- [beginner|intermediate|expert]
+//   cargo run --bin terminal -- <width> <height> <mines>
+//
+// Arrow keys or hjkl move the cursor, space opens a field or chords on an
+// open one, f toggles a flag, u and r undo and redo, n starts a new game and
+// q quits.
+
+const USAGE: &str = "usage: terminal [beginner|intermediate|expert]
+       terminal <width> <height> <mines>";
+
+type Dimensions = (usize, usize, usize);
+
+struct Game {
+  ms: Minesweeper,
+  dimensions: Dimensions,
+  cursor: (usize, usize),
+}
+
+impl Game {
+  fn new(dimensions: Dimensions) -> Result<Game, MinesweeperError> {
+    let (width, height, mine_count) = dimensions;
+    let options = GameOptions {
+      first_click: FirstClick::SafeNeighborhood,
+      ..GameOptions::default()
+    };
+
+    Ok(Game {
+      ms: Minesweeper::try_with_options(width, height, mine_count, options)?,
+      dimensions,
+      cursor: (width / 2, height / 2),
+    })
+  }
+
+  fn move_cursor(&mut self, dx: isize, dy: isize) {
+    let (x, y) = self.cursor;
+
+    self.cursor = (
+      x.saturating_add_signed(dx).min(self.ms.width() - 1),
+      y.saturating_add_signed(dy).min(self.ms.height() - 1),
+    );
+  }
+}
+
+fn parse_args(args: &[String]) -> Option<Dimensions> {
+  match args {
+    [] => Some(Difficulty::Beginner.dimensions()),
+    [name] => Difficulty::from_name(name).map(Difficulty::dimensions),
+    [width, height, mines] => Some((
+      width.parse().ok()?,
+      height.parse().ok()?,
+      mines.parse().ok()?,
+    )),
+    _ => None,
+  }
+}
+
+// Uses the symbols and colors of the ANSI theme, the cursor is shown in
+// reverse video
+fn field_text(field: FieldState, selected: bool) -> String {
+  let reverse = if selected { ";7" } else { "" };
+
+  format!(
+    "\x1b[{}{}m{}\x1b[0m",
+    Ansi::style(field),
+    reverse,
+    Ascii::symbol(field)
+  )
+}
+
+fn draw(out: &mut impl Write, game: &Game) -> io::Result<()> {
+  let snapshot = game.ms.snapshot();
+  let mut flag_count = 0;
+
+  for y in 0..snapshot.height {
+    queue!(out, MoveTo(0, y as u16))?;
+
+    for x in 0..snapshot.width {
+      let field = snapshot.get((x, y));
+
+      if matches!(field, FieldState::Flagged | FieldState::WrongFlag) {
+        flag_count += 1;
+      }
+
+      queue!(
+        out,
+        Print(' '),
+        Print(field_text(field, (x, y) == game.cursor))
+      )?;
+    }
+  }
+
+  let status = match snapshot.status {
+    GameStatus::Playing => "",
+    GameStatus::Won => "You won!",
+    GameStatus::Lost => "You lost.",
+  };
+  let mines_left = game.ms.mine_count() as isize - flag_count;
+  let seconds = game.ms.stats().time / 1000;
+
+  queue!(
+    out,
+    MoveTo(0, snapshot.height as u16 + 1),
+    Clear(ClearType::UntilNewLine),
+    Print(format!(
+      " Mines: {}  Time: {}s  {}",
+      mines_left, seconds, status
+    )),
+    MoveTo(0, snapshot.height as u16 + 2),
+    Print(
+      " Space open  F flag  U undo  R redo  N new game  Q quit".dark_grey()
+    ),
+  )?;
+
+  out.flush()
+}
+
+fn run(out: &mut impl Write, mut game: Game) -> io::Result<()> {
+  loop {
+    draw(out, &game)?;
+
+    // Keep redrawing without input so the timer keeps running
+    if !event::poll(Duration::from_millis(250))? {
+      continue;
+    }
+
+    let code = match event::read()? {
+      Event::Key(KeyEvent {
+        code,
+        kind: KeyEventKind::Press,
+        ..
+      }) => code,
+      Event::Resize(..) => {
+        queue!(out, Clear(ClearType::All))?;
+        continue;
+      }
+      _ => continue,
+    };
+
+    match code {
+      KeyCode::Left | KeyCode::Char('h') => game.move_cursor(-1, 0),
+      KeyCode::Right | KeyCode::Char('l') => game.move_cursor(1, 0),
+      KeyCode::Up | KeyCode::Char('k') => game.move_cursor(0, -1),
+      KeyCode::Down | KeyCode::Char('j') => game.move_cursor(0, 1),
+      KeyCode::Char(' ') | KeyCode::Enter => {
+        game.ms.open(game.cursor);
+      }
+      KeyCode::Char('f') => {
+        game.ms.toggle_flag(game.cursor);
+      }
+      KeyCode::Char('u') => {
+        game.ms.undo();
+      }
+      KeyCode::Char('r') => {
+        game.ms.redo();
+      }
+      KeyCode::Char('n') => {
+        game = Game::new(game.dimensions).map_err(io::Error::other)?;
+        queue!(out, Clear(ClearType::All))?;
+      }
+      KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+      _ => {}
+    }
+  }
+}
+
+fn main() {
+  let args = env::args().skip(1).collect::<Vec<_>>();
+  let dimensions = parse_args(&args).unwrap_or_else(|| {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+  });
+  let game = Game::new(dimensions).unwrap_or_else(|err| {
+    eprintln!("error: {}", err);
+    process::exit(2);
+  });
+
+  let mut out = io::stdout();
+  let result = enable_raw_mode()
+    .and_then(|_| execute!(out, EnterAlternateScreen, Hide))
+    .and_then(|_| run(&mut out, game));
+
+  // Always restore the terminal, even if drawing failed
+  let _ = execute!(out, Show, LeaveAlternateScreen);
+  let _ = disable_raw_mode();
+
+  if let Err(err) = result {
+    eprintln!("error: {}", err);
+    process::exit(1);
+  }
+}
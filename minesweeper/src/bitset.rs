@@ -0,0 +1,87 @@
+// A fixed-size set of field indices with one bit per field, much smaller and
+// faster than a `HashSet<Position>` on large boards
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BitSet {
+  words: Vec<u64>,
+  len: usize,
+}
+
+impl BitSet {
+  pub fn new(capacity: usize) -> BitSet {
+    BitSet {
+      words: vec![0; capacity.div_ceil(64)],
+      len: 0,
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  pub fn contains(&self, index: usize) -> bool {
+    self.words[index / 64] & (1 << (index % 64)) != 0
+  }
+
+  // Returns whether the index was not already in the set
+  pub fn insert(&mut self, index: usize) -> bool {
+    let word = &mut self.words[index / 64];
+    let bit = 1 << (index % 64);
+    let inserted = *word & bit == 0;
+
+    *word |= bit;
+    self.len += inserted as usize;
+    inserted
+  }
+
+  // Returns whether the index was in the set
+  pub fn remove(&mut self, index: usize) -> bool {
+    let word = &mut self.words[index / 64];
+    let bit = 1 << (index % 64);
+    let removed = *word & bit != 0;
+
+    *word &= !bit;
+    self.len -= removed as usize;
+    removed
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+    self.words.iter().enumerate().flat_map(|(i, &word)| {
+      let mut rest = word;
+
+      std::iter::from_fn(move || {
+        (rest != 0).then(|| {
+          let bit = rest.trailing_zeros() as usize;
+          rest &= rest - 1;
+          i * 64 + bit
+        })
+      })
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::BitSet;
+
+  #[test]
+  fn insert_and_remove() {
+    let mut set = BitSet::new(130);
+
+    assert!(set.insert(0));
+    assert!(set.insert(129));
+    assert!(!set.insert(129));
+    assert!(set.insert(64));
+    assert_eq!(set.len(), 3);
+    assert_eq!(set.iter().collect::<Vec<_>>(), [0, 64, 129]);
+
+    assert!(set.remove(64));
+    assert!(!set.remove(64));
+    assert!(!set.contains(64));
+    assert!(set.contains(129));
+    assert_eq!(set.len(), 2);
+  }
+}
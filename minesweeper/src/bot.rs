@@ -0,0 +1,194 @@
+use crate::{
+  minesweeper::{GameStatus, Minesweeper, Position},
+  random::{RandomSource, SplitMix64},
+  solver,
+  topology::Topology,
+};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GuessPolicy {
+  // Opens the field with the lowest mine probability
+  #[default]
+  SafestField,
+  // Opens a random field that is not known to be a mine
+  Random,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BotResult {
+  pub won: bool,
+  // Opened fields that could not be deduced, not counting the first click
+  pub guesses: usize,
+  // Every opened field counts as one move, the bot never flags or chords
+  pub moves: usize,
+  pub three_bv: usize,
+}
+
+impl BotResult {
+  // 3BV per move, 1.0 means the bot needed no more moves than a perfect
+  // player
+  pub fn efficiency(&self) -> f64 {
+    if self.moves == 0 {
+      0.0
+    } else {
+      self.three_bv as f64 / self.moves as f64
+    }
+  }
+}
+
+// Plays games without any user interaction, opening fields the solver proves
+// safe and guessing according to its policy when stuck
+#[derive(Debug)]
+pub struct Bot {
+  policy: GuessPolicy,
+  rng: SplitMix64,
+}
+
+impl Bot {
+  pub fn new(policy: GuessPolicy, seed: u64) -> Bot {
+    Bot {
+      policy,
+      rng: SplitMix64::new(seed),
+    }
+  }
+
+  pub fn play<T: Topology>(&mut self, ms: &mut Minesweeper<T>) -> BotResult {
+    let mut result = BotResult::default();
+
+    // Every game starts in the center, which is never counted as a guess even
+    // if the mines were placed before the first click
+    if ms.history().is_empty() {
+      ms.open((ms.width() / 2, ms.height() / 2));
+      result.moves += 1;
+    }
+
+    while ms.status() == GameStatus::Playing {
+      let solution = solver::solve(ms);
+
+      if !solution.safe.is_empty() {
+        for pos in solution.safe {
+          // Earlier openings of the same batch may have cascaded over it,
+          // opening it again would be a chord
+          if ms.status() != GameStatus::Playing || ms.is_open(pos) {
+            continue;
+          }
+
+          ms.open(pos);
+          result.moves += 1;
+        }
+
+        continue;
+      }
+
+      let pos = match self.guess(ms, &solution.mines) {
+        Some(pos) => pos,
+        None => break,
+      };
+
+      ms.open(pos);
+      result.moves += 1;
+      result.guesses += 1;
+    }
+
+    result.won = ms.status() == GameStatus::Won;
+    result.three_bv = ms.stats().three_bv;
+    result
+  }
+
+  fn guess<T: Topology>(
+    &mut self,
+    ms: &Minesweeper<T>,
+    known_mines: &BTreeSet<Position>,
+  ) -> Option<Position> {
+    match self.policy {
+      GuessPolicy::SafestField => ms
+        .mine_probabilities()
+        .into_iter()
+        .filter(|(pos, _)| !known_mines.contains(pos))
+        // Ties are broken by position to keep games reproducible
+        .min_by(|(a, p), (b, q)| p.total_cmp(q).then(a.cmp(b)))
+        .map(|(pos, _)| pos),
+      GuessPolicy::Random => {
+        let candidates = ms
+          .iter_positions()
+          .filter(|&pos| {
+            !ms.is_open(pos)
+              && !ms.is_flagged(pos)
+              && !known_mines.contains(&pos)
+          })
+          .collect::<Vec<_>>();
+
+        (!candidates.is_empty())
+          .then(|| candidates[self.rng.range(0, candidates.len())])
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Bot, GuessPolicy};
+  use crate::{
+    minesweeper::{Action, FirstClick, GameOptions, GameStatus, Generation},
+    Minesweeper,
+  };
+
+  #[test]
+  fn no_guess_boards_need_no_guesses() {
+    for seed in 0..5 {
+      let mut ms = Minesweeper::with_options(
+        9,
+        9,
+        10,
+        GameOptions {
+          seed: Some(seed),
+          first_click: FirstClick::SafeNeighborhood,
+          generation: Generation::NoGuess { max_attempts: 200 },
+          ..GameOptions::default()
+        },
+      );
+      let result = Bot::new(GuessPolicy::SafestField, 0).play(&mut ms);
+
+      assert!(result.won);
+      assert_eq!(result.guesses, 0);
+      assert_eq!(result.moves, ms.history().len());
+    }
+  }
+
+  #[test]
+  fn games_are_reproducible() {
+    for policy in [GuessPolicy::SafestField, GuessPolicy::Random] {
+      let results = (0..2)
+        .map(|_| {
+          let mut ms = Minesweeper::with_seed(16, 16, 40, 9);
+          Bot::new(policy, 1).play(&mut ms)
+        })
+        .collect::<Vec<_>>();
+
+      assert_eq!(results[0], results[1]);
+    }
+  }
+
+  #[test]
+  fn first_click_is_not_a_guess() {
+    // An unprotected board that already has a mine in the center
+    let mut ms = (0..)
+      .map(|seed| {
+        let options = GameOptions {
+          seed: Some(seed),
+          first_click: FirstClick::Unprotected,
+          ..GameOptions::default()
+        };
+
+        Minesweeper::with_options(9, 9, 20, options)
+      })
+      .find(|ms| ms.is_mine((4, 4)))
+      .unwrap();
+    let result = Bot::new(GuessPolicy::SafestField, 0).play(&mut ms);
+
+    assert_eq!(ms.history()[0].action, Action::Open((4, 4)));
+    assert_eq!(ms.status(), GameStatus::Lost);
+    assert_eq!((result.moves, result.guesses), (1, 0));
+  }
+}
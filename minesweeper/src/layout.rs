@@ -0,0 +1,211 @@
+use crate::{
+  minesweeper::{Minesweeper, MinesweeperError},
+  topology::{Grid, Topology},
+};
+use std::{
+  collections::HashSet,
+  error::Error,
+  fmt::{self, Display},
+  str::FromStr,
+};
+
+// Boards can be written down as plain text, one line per row:
+//
+// .  covered field
+// *  covered mine
+// o  open field
+// x  open mine
+// f  flagged field without mine
+// F  flagged mine
+//
+// Whitespace around rows is ignored, so layouts can be indented.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutError {
+  Empty,
+  UnevenRow {
+    line: usize,
+  },
+  InvalidCharacter {
+    line: usize,
+    column: usize,
+    ch: char,
+  },
+  InvalidBoard(MinesweeperError),
+}
+
+impl Display for LayoutError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      LayoutError::Empty => f.write_str("layout contains no fields"),
+      LayoutError::UnevenRow { line } => {
+        write!(f, "row on line {} has a different length", line)
+      }
+      LayoutError::InvalidCharacter { line, column, ch } => write!(
+        f,
+        "invalid character '{}' on line {}, column {}",
+        ch, line, column
+      ),
+      LayoutError::InvalidBoard(err) => write!(f, "invalid board: {}", err),
+    }
+  }
+}
+
+impl Error for LayoutError {}
+
+impl FromStr for Minesweeper {
+  type Err = LayoutError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let rows = s
+      .lines()
+      .enumerate()
+      .map(|(i, line)| (i + 1, line.trim()))
+      .filter(|(_, line)| !line.is_empty())
+      .collect::<Vec<_>>();
+    let width = rows
+      .first()
+      .map(|(_, row)| row.chars().count())
+      .ok_or(LayoutError::Empty)?;
+    let mut mines = HashSet::new();
+    let mut open_fields = HashSet::new();
+    let mut flagged_fields = HashSet::new();
+
+    for (y, &(line, row)) in rows.iter().enumerate() {
+      if row.chars().count() != width {
+        return Err(LayoutError::UnevenRow { line });
+      }
+
+      for (x, ch) in row.chars().enumerate() {
+        let pos = (x, y);
+        let (mine, open, flagged) = match ch {
+          '.' => (false, false, false),
+          '*' => (true, false, false),
+          'o' => (false, true, false),
+          'x' => (true, true, false),
+          'f' => (false, false, true),
+          'F' => (true, false, true),
+          _ => {
+            return Err(LayoutError::InvalidCharacter {
+              line,
+              column: x + 1,
+              ch,
+            })
+          }
+        };
+
+        if mine {
+          mines.insert(pos);
+        }
+
+        if open {
+          open_fields.insert(pos);
+        }
+
+        if flagged {
+          flagged_fields.insert(pos);
+        }
+      }
+    }
+
+    Minesweeper::from_parts(
+      Grid,
+      width,
+      rows.len(),
+      mines,
+      open_fields,
+      flagged_fields,
+    )
+    .map_err(LayoutError::InvalidBoard)
+  }
+}
+
+impl<T: Topology> Minesweeper<T> {
+  // Exports the board in the format accepted by `FromStr`, including mines
+  pub fn to_layout(&self) -> String {
+    let mut result = String::new();
+
+    for y in 0..self.height() {
+      for x in 0..self.width() {
+        let pos = (x, y);
+
+        result.push(
+          match (self.is_mine(pos), self.is_open(pos), self.is_flagged(pos)) {
+            (false, true, _) => 'o',
+            (true, true, _) => 'x',
+            (false, false, true) => 'f',
+            (true, false, true) => 'F',
+            (false, false, false) => '.',
+            (true, false, false) => '*',
+          },
+        );
+      }
+
+      result.push('\n');
+    }
+
+    result
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::LayoutError;
+  use crate::{GameStatus, Minesweeper, MinesweeperError};
+
+  #[test]
+  fn round_trip() {
+    let layout = "o.*.\nF.f.\nx...\n";
+    let ms = layout.parse::<Minesweeper>().unwrap();
+
+    assert_eq!(ms.width(), 4);
+    assert_eq!(ms.height(), 3);
+    assert_eq!(ms.mine_count(), 3);
+    assert_eq!(ms.status(), GameStatus::Lost);
+    assert_eq!(ms.to_layout(), layout);
+  }
+
+  #[test]
+  fn open_cascade() {
+    let mut ms = "
+      .....
+      .....
+      ...*.
+      .....
+      *....
+    "
+    .parse::<Minesweeper>()
+    .unwrap();
+
+    ms.open((0, 0));
+
+    assert_eq!(ms.to_layout(), "ooooo\nooooo\nooo*.\nooo..\n*....\n");
+  }
+
+  #[test]
+  fn invalid_layouts() {
+    assert_eq!(
+      "\n  \n".parse::<Minesweeper>().unwrap_err(),
+      LayoutError::Empty
+    );
+    assert_eq!(
+      "...\n..\n".parse::<Minesweeper>().unwrap_err(),
+      LayoutError::UnevenRow { line: 2 }
+    );
+    assert_eq!(
+      "..?\n".parse::<Minesweeper>().unwrap_err(),
+      LayoutError::InvalidCharacter {
+        line: 1,
+        column: 3,
+        ch: '?'
+      }
+    );
+    assert_eq!(
+      "**\n**\n".parse::<Minesweeper>().unwrap_err(),
+      LayoutError::InvalidBoard(MinesweeperError::TooManyMines {
+        mine_count: 4,
+        field_count: 4
+      })
+    );
+  }
+}
@@ -1,30 +1,562 @@
-mod minesweeper;
-mod random;
+pub mod bitset;
+pub mod bot;
+pub mod layout;
+pub mod minesweeper;
+pub mod probability;
+pub mod random;
+pub mod render;
+pub mod replay;
+pub mod save;
+pub mod snapshot;
+pub mod solver;
+pub mod stats;
+pub mod time;
+pub mod topology;
+pub mod versus;
+pub mod view;
 
+use js_sys::Reflect;
 use minesweeper::*;
-use std::cell::RefCell;
-use wasm_bindgen::prelude::*;
+use replay::Replay;
+use snapshot::{ChangeSet, Snapshot};
+use solver::Hint;
+use stats::Stats;
+use std::{
+  cell::{Cell, RefCell},
+  collections::HashMap,
+};
+use topology::AnyTopology;
+use versus::{Match, MatchMode, MatchStatus, Player};
+use wasm_bindgen::{prelude::*, UnwrapThrowExt};
+
+#[wasm_bindgen]
+pub struct FieldHint {
+  pub x: usize,
+  pub y: usize,
+  #[wasm_bindgen(js_name = isMine)]
+  pub is_mine: bool,
+}
+
+#[wasm_bindgen]
+pub struct BoardSnapshot {
+  snapshot: Snapshot,
+}
+
+#[wasm_bindgen]
+impl BoardSnapshot {
+  // One of "grid", "torus", "hex" or "knight"
+  #[wasm_bindgen(getter)]
+  pub fn topology(&self) -> String {
+    self.snapshot.topology.clone()
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn width(&self) -> usize {
+    self.snapshot.width
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn height(&self) -> usize {
+    self.snapshot.height
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn status(&self) -> String {
+    self.snapshot.status.name().to_string()
+  }
+
+  // Row-major field codes, see `FieldState::to_code`
+  #[wasm_bindgen(getter)]
+  pub fn fields(&self) -> Vec<u8> {
+    self
+      .snapshot
+      .fields
+      .iter()
+      .map(|field| field.to_code())
+      .collect()
+  }
+}
+
+#[wasm_bindgen]
+pub struct BoardChanges {
+  width: usize,
+  changes: ChangeSet,
+}
+
+#[wasm_bindgen]
+impl BoardChanges {
+  #[wasm_bindgen(getter)]
+  pub fn status(&self) -> String {
+    self.changes.status.name().to_string()
+  }
+
+  // Row-major indices of the changed fields
+  #[wasm_bindgen(getter)]
+  pub fn indices(&self) -> Vec<u32> {
+    self
+      .changes
+      .fields
+      .iter()
+      .map(|&((x, y), _)| (y * self.width + x) as u32)
+      .collect()
+  }
+
+  // New field codes in the same order as `indices`, see `FieldState::to_code`
+  #[wasm_bindgen(getter)]
+  pub fn codes(&self) -> Vec<u8> {
+    self
+      .changes
+      .fields
+      .iter()
+      .map(|(_, field)| field.to_code())
+      .collect()
+  }
+}
+
+#[wasm_bindgen]
+pub struct GameStats {
+  stats: Stats,
+}
+
+#[wasm_bindgen]
+impl GameStats {
+  #[wasm_bindgen(getter, js_name = threeBv)]
+  pub fn three_bv(&self) -> usize {
+    self.stats.three_bv
+  }
+
+  #[wasm_bindgen(getter, js_name = leftClicks)]
+  pub fn left_clicks(&self) -> usize {
+    self.stats.left_clicks
+  }
+
+  #[wasm_bindgen(getter, js_name = rightClicks)]
+  pub fn right_clicks(&self) -> usize {
+    self.stats.right_clicks
+  }
+
+  #[wasm_bindgen(getter, js_name = chordClicks)]
+  pub fn chord_clicks(&self) -> usize {
+    self.stats.chord_clicks
+  }
+
+  // Milliseconds
+  #[wasm_bindgen(getter)]
+  pub fn time(&self) -> f64 {
+    self.stats.time as f64
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn efficiency(&self) -> Option<f64> {
+    self.stats.efficiency()
+  }
+
+  #[wasm_bindgen(getter, js_name = threeBvPerSecond)]
+  pub fn three_bv_per_second(&self) -> Option<f64> {
+    self.stats.three_bv_per_second()
+  }
+}
 
 thread_local! {
-  static MINESWEEPER: RefCell<Minesweeper>
-    = RefCell::new(Minesweeper::new(10, 10, 15));
+  static GAMES: RefCell<HashMap<u32, Minesweeper<AnyTopology>>> =
+    RefCell::new(HashMap::new());
+
+  static MATCHES: RefCell<HashMap<u32, Match<AnyTopology>>> =
+    RefCell::new(HashMap::new());
+
+  static NEXT_HANDLE: Cell<u32> = const { Cell::new(1) };
 }
 
+fn with_game<T>(
+  handle: u32,
+  f: impl FnOnce(&mut Minesweeper<AnyTopology>) -> T,
+) -> T {
+  GAMES.with(|games| {
+    f(games
+      .borrow_mut()
+      .get_mut(&handle)
+      .expect_throw("Unknown game handle"))
+  })
+}
+
+fn with_match<T>(
+  handle: u32,
+  f: impl FnOnce(&mut Match<AnyTopology>) -> T,
+) -> T {
+  MATCHES.with(|matches| {
+    f(matches
+      .borrow_mut()
+      .get_mut(&handle)
+      .expect_throw("Unknown match handle"))
+  })
+}
+
+fn get_option(options: &JsValue, key: &str) -> Option<JsValue> {
+  if !options.is_object() {
+    return None;
+  }
+
+  Reflect::get(options, &key.into())
+    .ok()
+    .filter(|value| !value.is_undefined() && !value.is_null())
+}
+
+// Reads the optional `topology` key, see `AnyTopology::from_name`
+fn parse_topology(options: &JsValue) -> AnyTopology {
+  get_option(options, "topology")
+    .and_then(|value| value.as_string())
+    .and_then(|value| AnyTopology::from_name(&value))
+    .unwrap_or_default()
+}
+
+// Reads `{ seed, firstClick, noGuess, maxAttempts, questionMarks, lives }` from
+// a JS object, all keys are optional
+fn parse_options(options: &JsValue) -> GameOptions {
+  let seed = get_option(options, "seed").and_then(|seed| {
+    seed
+      .as_f64()
+      .map(|seed| seed as u64)
+      .or_else(|| u64::try_from(seed).ok())
+  });
+  let first_click = get_option(options, "firstClick")
+    .and_then(|value| value.as_string())
+    .map(|value| match &value[..] {
+      "unprotected" => FirstClick::Unprotected,
+      "safeNeighborhood" => FirstClick::SafeNeighborhood,
+      _ => FirstClick::SafeField,
+    })
+    .unwrap_or_default();
+  let no_guess = get_option(options, "noGuess")
+    .and_then(|value| value.as_bool())
+    .unwrap_or(false);
+  let max_attempts = get_option(options, "maxAttempts")
+    .and_then(|value| value.as_f64())
+    .map(|value| value as usize)
+    .unwrap_or(1000);
+  let question_marks = get_option(options, "questionMarks")
+    .and_then(|value| value.as_bool())
+    .unwrap_or(false);
+  let lives = get_option(options, "lives")
+    .and_then(|value| value.as_f64())
+    .map(|value| value as u32);
+
+  GameOptions {
+    seed,
+    first_click,
+    generation: if no_guess {
+      Generation::NoGuess { max_attempts }
+    } else {
+      Generation::Random
+    },
+    flag_cycle: if question_marks {
+      FlagCycle::FlagAndQuestion
+    } else {
+      FlagCycle::Flag
+    },
+    lives,
+  }
+}
+
+fn next_handle() -> u32 {
+  NEXT_HANDLE.with(|next_handle| {
+    let handle = next_handle.get();
+    next_handle.set(handle + 1);
+    handle
+  })
+}
+
+fn insert_game(ms: Minesweeper<AnyTopology>) -> u32 {
+  let handle = next_handle();
+
+  GAMES.with(|games| games.borrow_mut().insert(handle, ms));
+
+  handle
+}
+
+// Throws if the dimensions or mine count are invalid
+#[wasm_bindgen(js_name = newGame)]
+pub fn new_game(
+  width: usize,
+  height: usize,
+  mines: usize,
+  options: JsValue,
+) -> Result<u32, JsError> {
+  let ms = Minesweeper::try_with_topology(
+    parse_topology(&options),
+    width,
+    height,
+    mines,
+    parse_options(&options),
+  )?;
+
+  Ok(insert_game(ms))
+}
+
+// Starts a game with one of "beginner", "intermediate" or "expert"
+#[wasm_bindgen(js_name = newGameWithDifficulty)]
+pub fn new_game_with_difficulty(
+  difficulty: &str,
+  options: JsValue,
+) -> Result<u32, JsError> {
+  let (width, height, mines) = Difficulty::from_name(difficulty)
+    .ok_or_else(|| JsError::new("Unknown difficulty"))?
+    .dimensions();
+
+  new_game(width, height, mines, options)
+}
+
+#[wasm_bindgen(js_name = dropGame)]
+pub fn drop_game(handle: u32) {
+  GAMES.with(|games| games.borrow_mut().remove(&handle));
+}
+
+// Renders the board as text with one of the themes "emoji" (default),
+// "ascii" or "ansi"
 #[wasm_bindgen(js_name = getState)]
-pub fn get_state() -> String {
-  MINESWEEPER.with(|ms| ms.borrow().to_string())
+pub fn get_state(
+  handle: u32,
+  theme: Option<String>,
+) -> Result<String, JsError> {
+  let renderer = match theme {
+    Some(theme) => {
+      render::from_name(&theme).ok_or_else(|| JsError::new("Unknown theme"))?
+    }
+    None => &render::Emoji,
+  };
+
+  Ok(with_game(handle, |ms| ms.render(renderer)))
+}
+
+#[wasm_bindgen(js_name = getSnapshot)]
+pub fn get_snapshot(handle: u32) -> BoardSnapshot {
+  with_game(handle, |ms| BoardSnapshot {
+    snapshot: ms.snapshot(),
+  })
 }
 
 #[wasm_bindgen(js_name = openField)]
-pub fn open_field(x: usize, y: usize) {
-  MINESWEEPER.with(|ms| {
-    ms.borrow_mut().open((x, y));
-  });
+pub fn open_field(handle: u32, x: usize, y: usize) -> BoardChanges {
+  with_game(handle, |ms| BoardChanges {
+    width: ms.width(),
+    changes: ms.open((x, y)),
+  })
 }
 
 #[wasm_bindgen(js_name = toggleFlag)]
-pub fn toggle_flag(x: usize, y: usize) {
-  MINESWEEPER.with(|ms| {
-    ms.borrow_mut().toggle_flag((x, y));
-  });
+pub fn toggle_flag(handle: u32, x: usize, y: usize) -> BoardChanges {
+  with_game(handle, |ms| BoardChanges {
+    width: ms.width(),
+    changes: ms.toggle_flag((x, y)),
+  })
+}
+
+#[wasm_bindgen(js_name = getSeed)]
+pub fn get_seed(handle: u32) -> Option<u64> {
+  with_game(handle, |ms| ms.seed())
+}
+
+#[wasm_bindgen(js_name = getHint)]
+pub fn get_hint(handle: u32) -> Option<FieldHint> {
+  with_game(handle, |ms| {
+    ms.hint().map(|hint| match hint {
+      Hint::Safe((x, y)) => FieldHint {
+        x,
+        y,
+        is_mine: false,
+      },
+      Hint::Mine((x, y)) => FieldHint {
+        x,
+        y,
+        is_mine: true,
+      },
+    })
+  })
+}
+
+// Returns the mine probability of each field in row-major order, NaN for
+// fields that are open or flagged. Large frontiers only get an estimate so
+// the page does not freeze, see `Minesweeper::mine_probabilities`.
+#[wasm_bindgen(js_name = getProbabilities)]
+pub fn get_probabilities(handle: u32) -> Vec<f64> {
+  with_game(handle, |ms| {
+    let probabilities = ms.mine_probabilities();
+
+    ms.iter_positions()
+      .map(|pos| probabilities.get(&pos).copied().unwrap_or(f64::NAN))
+      .collect()
+  })
+}
+
+#[wasm_bindgen(js_name = getStats)]
+pub fn get_stats(handle: u32) -> GameStats {
+  with_game(handle, |ms| GameStats { stats: ms.stats() })
+}
+
+// Remaining lives, `undefined` for games with classic rules
+#[wasm_bindgen(js_name = getLives)]
+pub fn get_lives(handle: u32) -> Option<u32> {
+  with_game(handle, |ms| ms.lives_left())
+}
+
+#[wasm_bindgen(js_name = getStatus)]
+pub fn get_status(handle: u32) -> String {
+  with_game(handle, |ms| ms.status().name().to_string())
+}
+
+// `None` if the game was not generated in no-guess mode or no mines have been
+// placed yet
+#[wasm_bindgen(js_name = isGuessFree)]
+pub fn is_guess_free(handle: u32) -> Option<bool> {
+  with_game(handle, |ms| ms.guess_free())
+}
+
+#[wasm_bindgen]
+pub fn undo(handle: u32) -> bool {
+  with_game(handle, |ms| ms.undo())
+}
+
+#[wasm_bindgen]
+pub fn redo(handle: u32) -> bool {
+  with_game(handle, |ms| ms.redo())
+}
+
+// The game as a versioned string that `loadGame` can restore, for example
+// after a page reload
+#[wasm_bindgen(js_name = saveGame)]
+pub fn save_game(handle: u32) -> String {
+  with_game(handle, |ms| ms.save())
+}
+
+#[wasm_bindgen(js_name = loadGame)]
+pub fn load_game(saved: &str) -> Result<u32, JsError> {
+  Ok(insert_game(Minesweeper::load(saved)?))
+}
+
+#[wasm_bindgen(js_name = getReplay)]
+pub fn get_replay(handle: u32) -> String {
+  with_game(handle, |ms| Replay::from_game(ms).to_string())
+}
+
+// Throws if the replay cannot be parsed or does not reproduce its recorded
+// result, otherwise returns its duration in milliseconds
+#[wasm_bindgen(js_name = verifyReplay)]
+pub fn verify_replay(replay: &str) -> Result<u64, JsError> {
+  let replay = replay.parse::<Replay>()?;
+  replay.verify()?;
+
+  Ok(replay.duration())
+}
+
+fn player_number(player: Player) -> u8 {
+  match player {
+    Player::One => 1,
+    Player::Two => 2,
+  }
+}
+
+// Starts a hot-seat match for two players. Takes the same options as
+// `newGame` plus `mode`, which is "reveal" (default) or "flags".
+#[wasm_bindgen(js_name = newMatch)]
+pub fn new_match(
+  width: usize,
+  height: usize,
+  mines: usize,
+  options: JsValue,
+) -> Result<u32, JsError> {
+  let ms = Minesweeper::try_with_topology(
+    parse_topology(&options),
+    width,
+    height,
+    mines,
+    parse_options(&options),
+  )?;
+  let mode =
+    match get_option(&options, "mode").and_then(|mode| mode.as_string()) {
+      Some(mode) if mode == "flags" => MatchMode::Flags,
+      _ => MatchMode::Reveal,
+    };
+  let handle = next_handle();
+
+  MATCHES
+    .with(|matches| matches.borrow_mut().insert(handle, Match::new(ms, mode)));
+
+  Ok(handle)
+}
+
+#[wasm_bindgen(js_name = dropMatch)]
+pub fn drop_match(handle: u32) {
+  MATCHES.with(|matches| matches.borrow_mut().remove(&handle));
+}
+
+// Plays a turn for the current player, returns nothing if the field cannot
+// be opened
+#[wasm_bindgen(js_name = playTurn)]
+pub fn play_turn(handle: u32, x: usize, y: usize) -> Option<BoardChanges> {
+  with_match(handle, |game| {
+    game.play((x, y)).map(|turn| BoardChanges {
+      width: game.game().width(),
+      changes: turn.changes,
+    })
+  })
+}
+
+#[wasm_bindgen(js_name = getMatchSnapshot)]
+pub fn get_match_snapshot(handle: u32) -> BoardSnapshot {
+  with_match(handle, |game| BoardSnapshot {
+    snapshot: game.game().snapshot(),
+  })
+}
+
+// 1 or 2
+#[wasm_bindgen(js_name = getCurrentPlayer)]
+pub fn get_current_player(handle: u32) -> u8 {
+  with_match(handle, |game| player_number(game.current_player()))
+}
+
+// The scores of player 1 and 2
+#[wasm_bindgen(js_name = getScores)]
+pub fn get_scores(handle: u32) -> Vec<u32> {
+  with_match(handle, |game| {
+    [Player::One, Player::Two]
+      .map(|player| game.score(player) as u32)
+      .to_vec()
+  })
+}
+
+// Number of turns played so far
+#[wasm_bindgen(js_name = getTurns)]
+pub fn get_turns(handle: u32) -> u32 {
+  with_match(handle, |game| game.turns() as u32)
+}
+
+// One of "playing", "won" or "draw", see `getMatchWinner`
+#[wasm_bindgen(js_name = getMatchStatus)]
+pub fn get_match_status(handle: u32) -> String {
+  with_match(handle, |game| match game.status() {
+    MatchStatus::Playing => "playing",
+    MatchStatus::Won(_) => "won",
+    MatchStatus::Draw => "draw",
+  })
+  .to_string()
+}
+
+#[wasm_bindgen(js_name = getMatchWinner)]
+pub fn get_match_winner(handle: u32) -> Option<u8> {
+  with_match(handle, |game| match game.status() {
+    MatchStatus::Won(player) => Some(player_number(player)),
+    _ => None,
+  })
+}
+
+// Row-major player numbers of who claimed each mine in "flags" mode, 0 for
+// unclaimed fields
+#[wasm_bindgen(js_name = getClaims)]
+pub fn get_claims(handle: u32) -> Vec<u8> {
+  with_match(handle, |game| {
+    game
+      .game()
+      .iter_positions()
+      .map(|pos| game.owner(pos).map_or(0, player_number))
+      .collect()
+  })
 }
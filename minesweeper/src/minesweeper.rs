@@ -1,164 +1,1169 @@
-use crate::random::random_range;
+use crate::{
+  bitset::BitSet,
+  random::{random_seed, RandomSource, SplitMix64},
+  render::{BoardRenderer, Emoji},
+  snapshot::ChangeSet,
+  solver,
+  stats::Stats,
+  time,
+  topology::{Grid, Offset, Topology},
+};
 use std::{
-  collections::HashSet,
-  fmt::{Display, Write},
+  collections::{HashSet, VecDeque},
+  error::Error,
+  fmt::{self, Display},
 };
 
 pub type Position = (usize, usize);
 
-pub enum OpenResult {
-  Mine,
-  NoMine(u8),
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GameStatus {
+  #[default]
+  Playing,
+  Won,
+  Lost,
+}
+
+impl GameStatus {
+  // The name used by saved games, replays and the JavaScript API
+  pub fn name(self) -> &'static str {
+    match self {
+      GameStatus::Playing => "playing",
+      GameStatus::Won => "won",
+      GameStatus::Lost => "lost",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<GameStatus> {
+    Some(match name {
+      "playing" => GameStatus::Playing,
+      "won" => GameStatus::Won,
+      "lost" => GameStatus::Lost,
+      _ => return None,
+    })
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FirstClick {
+  // Mines are placed right away, the first click may hit a mine
+  Unprotected,
+  // The first opened field is never a mine
+  #[default]
+  SafeField,
+  // The first opened field and all its neighbors are never mines, so the
+  // first click always opens up an area
+  SafeNeighborhood,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Generation {
+  #[default]
+  Random,
+  // Retries layouts until the board can be cleared from the first click
+  // without guessing, giving up after `max_attempts` layouts. Mines are always
+  // placed on the first click in this mode.
+  NoGuess {
+    max_attempts: usize,
+  },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FlagCycle {
+  // Toggling cycles between covered and flagged
+  #[default]
+  Flag,
+  // Toggling cycles from covered to flagged to question mark and back to
+  // covered. Question marks are only reminders for the player, they don't
+  // count as flags when chording and can be opened.
+  FlagAndQuestion,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+  Open(Position),
+  // Opening an already open field opens all unflagged neighbors if enough
+  // neighbors are flagged
+  Chord(Position),
+  ToggleFlag(Position),
+}
+
+// An action together with all changes it caused on the board
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActionRecord {
+  pub action: Action,
+  // Milliseconds since the first action of the game
+  pub time: u64,
+  pub opened: Vec<Position>,
+  pub flagged: Vec<Position>,
+  pub unflagged: Vec<Position>,
+  pub marked: Vec<Position>,
+  pub unmarked: Vec<Position>,
+  // Mines that cost a life, they are also listed in `flagged`
+  pub detonated: Vec<Position>,
+  pub status_before: GameStatus,
+  pub status_after: GameStatus,
+}
+
+impl ActionRecord {
+  fn new(action: Action, time: u64, status: GameStatus) -> ActionRecord {
+    ActionRecord {
+      action,
+      time,
+      opened: vec![],
+      flagged: vec![],
+      unflagged: vec![],
+      marked: vec![],
+      unmarked: vec![],
+      detonated: vec![],
+      status_before: status,
+      status_after: status,
+    }
+  }
+
+  fn is_empty(&self) -> bool {
+    self.opened.is_empty()
+      && self.flagged.is_empty()
+      && self.unflagged.is_empty()
+      && self.marked.is_empty()
+      && self.unmarked.is_empty()
+      && self.status_before == self.status_after
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GameOptions {
+  pub seed: Option<u64>,
+  pub first_click: FirstClick,
+  pub generation: Generation,
+  pub flag_cycle: FlagCycle,
+  // Opening a mine costs a life and flags the mine instead of ending the game,
+  // which is lost when the last life is used up. `None` plays by the classic
+  // rules, which is the same as a single life.
+  pub lives: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+  // 9x9 with 10 mines
+  Beginner,
+  // 16x16 with 40 mines
+  Intermediate,
+  // 30x16 with 99 mines
+  Expert,
+}
+
+impl Difficulty {
+  pub fn from_name(name: &str) -> Option<Difficulty> {
+    Some(match name {
+      "beginner" => Difficulty::Beginner,
+      "intermediate" => Difficulty::Intermediate,
+      "expert" => Difficulty::Expert,
+      _ => return None,
+    })
+  }
+
+  // Width, height and mine count
+  pub fn dimensions(self) -> (usize, usize, usize) {
+    match self {
+      Difficulty::Beginner => (9, 9, 10),
+      Difficulty::Intermediate => (16, 16, 40),
+      Difficulty::Expert => (30, 16, 99),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MinesweeperError {
+  InvalidDimensions {
+    width: usize,
+    height: usize,
+  },
+  // At least one field has to be free of mines
+  TooManyMines {
+    mine_count: usize,
+    field_count: usize,
+  },
+  // The first click option needs more fields to be free of mines than the
+  // board has room for
+  TooFewSafeFields {
+    needed: usize,
+    available: usize,
+  },
+}
+
+impl Display for MinesweeperError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      MinesweeperError::InvalidDimensions { width, height } => {
+        write!(f, "invalid board dimensions {}x{}", width, height)
+      }
+      MinesweeperError::TooManyMines {
+        mine_count,
+        field_count,
+      } => write!(
+        f,
+        "{} mines do not fit on a board with {} fields",
+        mine_count, field_count
+      ),
+      MinesweeperError::TooFewSafeFields { needed, available } => write!(
+        f,
+        "first click needs {} fields without mines, but only {} are left",
+        needed, available
+      ),
+    }
+  }
+}
+
+impl Error for MinesweeperError {}
+
+// Boards need at least one field and must be small enough to index
+fn field_count(width: usize, height: usize) -> Result<usize, MinesweeperError> {
+  width
+    .checked_mul(height)
+    .filter(|&field_count| field_count > 0)
+    .ok_or(MinesweeperError::InvalidDimensions { width, height })
 }
 
 #[derive(Debug)]
-pub struct Minesweeper {
+pub struct Minesweeper<T: Topology = Grid> {
+  topology: T,
   width: usize,
   height: usize,
-  open_fields: HashSet<Position>,
-  mines: HashSet<Position>,
-  flagged_fields: HashSet<Position>,
-  lost: bool,
+  // Fields are stored densely in row-major order, see `index`
+  open_fields: BitSet,
+  mines: BitSet,
+  flagged_fields: BitSet,
+  // Question marks, only visible on fields that are neither open nor flagged
+  marked_fields: BitSet,
+  // Mines that cost a life, these are always flagged
+  detonated: BitSet,
+  // Number of neighboring mines of each field, updated when mines are placed
+  neighbor_counts: Vec<u8>,
+  status: GameStatus,
+  mine_count: usize,
+  first_click: FirstClick,
+  generation: Generation,
+  flag_cycle: FlagCycle,
+  lives: Option<u32>,
+  guess_free: Option<bool>,
+  seed: Option<u64>,
+  // Only set while mine placement is deferred until the first click
+  rng: Option<Box<dyn RandomSource>>,
+  history: Vec<ActionRecord>,
+  // Undone actions, the most recently undone action is last
+  redo_stack: Vec<ActionRecord>,
+  // Undone actions disappear from the history, so this is kept separately
+  undo_used: bool,
+  // Wall clock time of the first action in milliseconds
+  started_at: Option<f64>,
+  // The time is only kept up to date once the game has ended, see `stats`
+  stats: Stats,
 }
 
-impl Display for Minesweeper {
+// Uses the emoji theme, see `render` for other themes
+impl<T: Topology> Display for Minesweeper<T> {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    for y in 0..self.height {
-      for x in 0..self.width {
-        let pos = (x, y);
-
-        if !self.open_fields.contains(&pos) {
-          if self.lost && self.mines.contains(&pos) {
-            f.write_str("💣 ")?;
-          } else if self.flagged_fields.contains(&pos) {
-            f.write_str("🚩 ")?;
-          } else {
-            f.write_str("🟪 ")?;
-          }
-        } else if self.mines.contains(&pos) {
-          f.write_str("💣 ")?;
-        } else {
-          let mine_count = self.neighboring_mines(pos);
-
-          if mine_count > 0 {
-            write!(f, " {} ", mine_count)?;
-          } else {
-            f.write_str("⬜ ")?;
-          }
-        }
+    Emoji.write_board(f, &self.snapshot())
+  }
+}
+
+// The infallible constructors panic on invalid dimensions or mine counts, use
+// the `try_` variants for user-provided values
+impl Minesweeper {
+  pub fn new(width: usize, height: usize, mine_count: usize) -> Minesweeper {
+    Minesweeper::with_options(width, height, mine_count, GameOptions::default())
+  }
+
+  pub fn with_difficulty(
+    difficulty: Difficulty,
+    options: GameOptions,
+  ) -> Minesweeper {
+    let (width, height, mine_count) = difficulty.dimensions();
+
+    Minesweeper::with_options(width, height, mine_count, options)
+  }
+
+  pub fn try_with_options(
+    width: usize,
+    height: usize,
+    mine_count: usize,
+    options: GameOptions,
+  ) -> Result<Minesweeper, MinesweeperError> {
+    Minesweeper::try_with_topology(Grid, width, height, mine_count, options)
+  }
+
+  pub fn with_seed(
+    width: usize,
+    height: usize,
+    mine_count: usize,
+    seed: u64,
+  ) -> Minesweeper {
+    Minesweeper::with_options(
+      width,
+      height,
+      mine_count,
+      GameOptions {
+        seed: Some(seed),
+        ..GameOptions::default()
+      },
+    )
+  }
+
+  pub fn with_options(
+    width: usize,
+    height: usize,
+    mine_count: usize,
+    options: GameOptions,
+  ) -> Minesweeper {
+    Minesweeper::with_topology(Grid, width, height, mine_count, options)
+  }
+
+  pub fn with_rng(
+    width: usize,
+    height: usize,
+    mine_count: usize,
+    options: GameOptions,
+    rng: Box<dyn RandomSource>,
+  ) -> Minesweeper {
+    Minesweeper::with_topology_and_rng(
+      Grid, width, height, mine_count, options, rng,
+    )
+  }
+
+  pub fn with_mines(
+    width: usize,
+    height: usize,
+    mines: impl IntoIterator<Item = Position>,
+  ) -> Minesweeper {
+    Minesweeper::with_topology_and_mines(Grid, width, height, mines)
+  }
+}
+
+impl<T: Topology> Minesweeper<T> {
+  pub fn with_topology(
+    topology: T,
+    width: usize,
+    height: usize,
+    mine_count: usize,
+    options: GameOptions,
+  ) -> Minesweeper<T> {
+    Minesweeper::try_with_topology(topology, width, height, mine_count, options)
+      .unwrap_or_else(|err| panic!("{}", err))
+  }
+
+  pub fn try_with_topology(
+    topology: T,
+    width: usize,
+    height: usize,
+    mine_count: usize,
+    options: GameOptions,
+  ) -> Result<Minesweeper<T>, MinesweeperError> {
+    let seed = options.seed.unwrap_or_else(random_seed);
+
+    Ok(Minesweeper {
+      seed: Some(seed),
+      ..Minesweeper::try_with_topology_and_rng(
+        topology,
+        width,
+        height,
+        mine_count,
+        options,
+        Box::new(SplitMix64::new(seed)),
+      )?
+    })
+  }
+
+  pub fn with_topology_and_rng(
+    topology: T,
+    width: usize,
+    height: usize,
+    mine_count: usize,
+    options: GameOptions,
+    rng: Box<dyn RandomSource>,
+  ) -> Minesweeper<T> {
+    Minesweeper::try_with_topology_and_rng(
+      topology, width, height, mine_count, options, rng,
+    )
+    .unwrap_or_else(|err| panic!("{}", err))
+  }
+
+  pub fn try_with_topology_and_rng(
+    topology: T,
+    width: usize,
+    height: usize,
+    mine_count: usize,
+    options: GameOptions,
+    rng: Box<dyn RandomSource>,
+  ) -> Result<Minesweeper<T>, MinesweeperError> {
+    let field_count = field_count(width, height)?;
+
+    if mine_count >= field_count {
+      return Err(MinesweeperError::TooManyMines {
+        mine_count,
+        field_count,
+      });
+    }
+
+    let mut result = Minesweeper {
+      topology,
+      width,
+      height,
+      open_fields: BitSet::new(field_count),
+      mines: BitSet::new(field_count),
+      flagged_fields: BitSet::new(field_count),
+      marked_fields: BitSet::new(field_count),
+      detonated: BitSet::new(field_count),
+      neighbor_counts: vec![0; field_count],
+      status: GameStatus::Playing,
+      mine_count,
+      first_click: options.first_click,
+      generation: options.generation,
+      flag_cycle: options.flag_cycle,
+      lives: options.lives.map(|lives| lives.max(1)),
+      guess_free: None,
+      seed: None,
+      rng: Some(rng),
+      history: vec![],
+      redo_stack: vec![],
+      undo_used: false,
+      started_at: None,
+      stats: Stats::default(),
+    };
+
+    let needed = match options.first_click {
+      FirstClick::Unprotected => 0,
+      FirstClick::SafeField => 1,
+      FirstClick::SafeNeighborhood => {
+        result
+          .iter_positions()
+          .map(|pos| result.iter_neighbors(pos).count())
+          .max()
+          .unwrap_or(0)
+          + 1
       }
+    };
 
-      f.write_char('\n')?;
+    if field_count - mine_count < needed {
+      return Err(MinesweeperError::TooFewSafeFields {
+        needed,
+        available: field_count - mine_count,
+      });
     }
 
-    Ok(())
+    if options.first_click == FirstClick::Unprotected
+      && options.generation == Generation::Random
+    {
+      result.place_mines(&HashSet::new());
+    }
+
+    Ok(result)
   }
-}
 
-impl Minesweeper {
-  pub fn new(width: usize, height: usize, mine_count: usize) -> Minesweeper {
-    Minesweeper {
+  pub fn with_topology_and_mines(
+    topology: T,
+    width: usize,
+    height: usize,
+    mines: impl IntoIterator<Item = Position>,
+  ) -> Minesweeper<T> {
+    Minesweeper::try_with_topology_and_mines(topology, width, height, mines)
+      .unwrap_or_else(|err| panic!("{}", err))
+  }
+
+  // Mines outside of the board are ignored
+  pub fn try_with_topology_and_mines(
+    topology: T,
+    width: usize,
+    height: usize,
+    mines: impl IntoIterator<Item = Position>,
+  ) -> Result<Minesweeper<T>, MinesweeperError> {
+    let field_count = field_count(width, height)?;
+    let mut result = Minesweeper {
+      topology,
       width,
       height,
-      open_fields: HashSet::new(),
-      mines: {
-        let mut mines = HashSet::new();
+      open_fields: BitSet::new(field_count),
+      mines: BitSet::new(field_count),
+      flagged_fields: BitSet::new(field_count),
+      marked_fields: BitSet::new(field_count),
+      detonated: BitSet::new(field_count),
+      neighbor_counts: vec![0; field_count],
+      status: GameStatus::Playing,
+      mine_count: 0,
+      first_click: FirstClick::Unprotected,
+      generation: Generation::Random,
+      flag_cycle: FlagCycle::Flag,
+      lives: None,
+      guess_free: None,
+      seed: None,
+      rng: None,
+      history: vec![],
+      redo_stack: vec![],
+      undo_used: false,
+      started_at: None,
+      stats: Stats::default(),
+    };
+    let mut layout = BitSet::new(field_count);
 
-        while mines.len() < mine_count {
-          mines.insert((random_range(0, width), random_range(0, height)));
-        }
+    for pos in mines {
+      if let Some(i) = result.index(pos) {
+        layout.insert(i);
+      }
+    }
 
-        mines
-      },
-      flagged_fields: HashSet::new(),
-      lost: false,
+    if layout.len() >= field_count {
+      return Err(MinesweeperError::TooManyMines {
+        mine_count: layout.len(),
+        field_count,
+      });
+    }
+
+    result.mine_count = layout.len();
+    result.set_mines(layout);
+    Ok(result)
+  }
+
+  // Builds a game in the given state, deriving the status from the open fields
+  pub(crate) fn from_parts(
+    topology: T,
+    width: usize,
+    height: usize,
+    mines: HashSet<Position>,
+    open_fields: HashSet<Position>,
+    flagged_fields: HashSet<Position>,
+  ) -> Result<Minesweeper<T>, MinesweeperError> {
+    let mut result =
+      Minesweeper::try_with_topology_and_mines(topology, width, height, mines)?;
+
+    for pos in open_fields {
+      if let Some(i) = result.index(pos) {
+        result.open_fields.insert(i);
+      }
+    }
+
+    for pos in flagged_fields {
+      if let Some(i) = result.index(pos) {
+        result.flagged_fields.insert(i);
+      }
+    }
+
+    if result.open_fields.iter().any(|i| result.mines.contains(i)) {
+      result.status = GameStatus::Lost;
+    } else {
+      result.check_won();
+    }
+
+    Ok(result)
+  }
+
+  // Restores what a loaded game cannot get from replaying its history,
+  // restarting the clock so that it continues from the saved time
+  pub(crate) fn restore(
+    &mut self,
+    options: GameOptions,
+    guess_free: Option<bool>,
+    undo_used: bool,
+    stats: Stats,
+  ) {
+    self.seed = options.seed;
+    self.first_click = options.first_click;
+    self.generation = options.generation;
+    self.guess_free = guess_free;
+    self.undo_used = undo_used;
+    self.stats = Stats {
+      three_bv: self.stats.three_bv,
+      ..stats
+    };
+
+    if stats.clicks() > 0 {
+      self.started_at = Some(time::now() - stats.time as f64);
+    }
+  }
+
+  fn place_mines(&mut self, excluded: &HashSet<Position>) {
+    let mut rng = match self.rng.take() {
+      Some(rng) => rng,
+      None => return,
+    };
+
+    let mines = self.random_mines(rng.as_mut(), excluded);
+    self.set_mines(mines);
+  }
+
+  fn random_mines(
+    &self,
+    rng: &mut dyn RandomSource,
+    excluded: &HashSet<Position>,
+  ) -> BitSet {
+    let mut mines = BitSet::new(self.width * self.height);
+
+    while mines.len() < self.mine_count {
+      let pos = (rng.range(0, self.width), rng.range(0, self.height));
+
+      if !excluded.contains(&pos) {
+        mines.insert(self.unchecked_index(pos));
+      }
     }
+
+    mines
+  }
+
+  fn set_mines(&mut self, mines: BitSet) {
+    // Custom offsets don't have to be symmetric, so every field counts the
+    // mines it sees instead of each mine adding itself to its neighbors
+    let neighbor_counts = (0..self.width * self.height)
+      .map(|i| {
+        self
+          .iter_neighbors(self.position(i))
+          .filter(|&neighbor| mines.contains(self.unchecked_index(neighbor)))
+          .count()
+          .min(u8::MAX as usize) as u8
+      })
+      .collect();
+
+    self.mines = mines;
+    self.neighbor_counts = neighbor_counts;
+    self.stats.three_bv = self.count_three_bv();
+  }
+
+  fn place_guess_free_mines(
+    &mut self,
+    pos: Position,
+    excluded: &HashSet<Position>,
+    max_attempts: usize,
+  ) {
+    let mut rng = match self.rng.take() {
+      Some(rng) => rng,
+      None => return,
+    };
+
+    self.guess_free = Some(false);
+
+    for _ in 0..max_attempts.max(1) {
+      let mines = self.random_mines(rng.as_mut(), excluded);
+      self.set_mines(mines);
+
+      let mut trial = Minesweeper::with_topology_and_mines(
+        self.topology.clone(),
+        self.width,
+        self.height,
+        self.iter_mines(),
+      );
+      trial.open(pos);
+
+      if solver::solve_to_end(&mut trial) {
+        self.guess_free = Some(true);
+        break;
+      }
+    }
+  }
+
+  // Construction already checked that the safe fields leave enough room for
+  // the mines
+  fn place_mines_around(&mut self, pos: Position) {
+    let mut excluded = HashSet::new();
+
+    if self.first_click == FirstClick::SafeNeighborhood {
+      excluded.extend(self.iter_neighbors(pos));
+    }
+
+    excluded.insert(pos);
+
+    match self.generation {
+      Generation::Random => self.place_mines(&excluded),
+      Generation::NoGuess { max_attempts } => {
+        self.place_guess_free_mines(pos, &excluded, max_attempts)
+      }
+    }
+  }
+
+  pub fn first_click(&self) -> FirstClick {
+    self.first_click
+  }
+
+  pub fn generation(&self) -> Generation {
+    self.generation
+  }
+
+  pub fn flag_cycle(&self) -> FlagCycle {
+    self.flag_cycle
+  }
+
+  // Existing question marks stay when switching to `FlagCycle::Flag`, but
+  // toggling them removes them
+  pub fn set_flag_cycle(&mut self, flag_cycle: FlagCycle) {
+    self.flag_cycle = flag_cycle;
+  }
+
+  // The total number of lives, `None` if the game uses classic rules
+  pub fn lives(&self) -> Option<u32> {
+    self.lives
+  }
+
+  pub fn set_lives(&mut self, lives: Option<u32>) {
+    self.lives = lives.map(|lives| lives.max(1));
+  }
+
+  // `None` if the game uses classic rules
+  pub fn lives_left(&self) -> Option<u32> {
+    self.lives.map(|lives| {
+      if self.is_lost() {
+        0
+      } else {
+        lives.saturating_sub(self.detonated.len() as u32)
+      }
+    })
+  }
+
+  pub(crate) fn iter_mines(&self) -> impl Iterator<Item = Position> + '_ {
+    self.mines.iter().map(|i| self.position(i))
+  }
+
+  pub fn mines_placed(&self) -> bool {
+    self.rng.is_none()
+  }
+
+  // Whether no-guess generation found a layout that is solvable without
+  // guessing, `None` if the board was not generated in no-guess mode
+  pub fn guess_free(&self) -> Option<bool> {
+    self.guess_free
+  }
+
+  pub fn seed(&self) -> Option<u64> {
+    self.seed
+  }
+
+  pub fn topology(&self) -> &T {
+    &self.topology
+  }
+
+  pub fn width(&self) -> usize {
+    self.width
+  }
+
+  pub fn height(&self) -> usize {
+    self.height
+  }
+
+  pub fn mine_count(&self) -> usize {
+    self.mine_count
+  }
+
+  pub fn status(&self) -> GameStatus {
+    self.status
+  }
+
+  pub fn is_lost(&self) -> bool {
+    self.status == GameStatus::Lost
+  }
+
+  // Index of a field in the dense storage, `None` if outside of the board
+  fn index(&self, pos: Position) -> Option<usize> {
+    (pos.0 < self.width && pos.1 < self.height)
+      .then(|| self.unchecked_index(pos))
+  }
+
+  fn unchecked_index(&self, (x, y): Position) -> usize {
+    y * self.width + x
+  }
+
+  fn position(&self, index: usize) -> Position {
+    (index % self.width, index / self.width)
+  }
+
+  pub fn is_open(&self, pos: Position) -> bool {
+    self
+      .index(pos)
+      .is_some_and(|i| self.open_fields.contains(i))
+  }
+
+  pub fn is_flagged(&self, pos: Position) -> bool {
+    self
+      .index(pos)
+      .is_some_and(|i| self.flagged_fields.contains(i))
+  }
+
+  pub fn is_marked(&self, pos: Position) -> bool {
+    self.index(pos).is_some_and(|i| {
+      self.marked_fields.contains(i)
+        && !self.open_fields.contains(i)
+        && !self.flagged_fields.contains(i)
+    })
+  }
+
+  // Whether the field is a mine that cost a life
+  pub fn is_detonated(&self, pos: Position) -> bool {
+    self.index(pos).is_some_and(|i| self.detonated.contains(i))
+  }
+
+  pub(crate) fn is_mine(&self, pos: Position) -> bool {
+    self.index(pos).is_some_and(|i| self.mines.contains(i))
+  }
+
+  pub fn iter_positions(&self) -> impl Iterator<Item = Position> {
+    let width = self.width;
+
+    (0..self.height).flat_map(move |y| (0..width).map(move |x| (x, y)))
   }
 
   pub fn iter_neighbors(
     &self,
     (x, y): Position,
-  ) -> impl Iterator<Item = Position> {
-    let width = self.width;
-    let height = self.height;
+  ) -> impl Iterator<Item = Position> + '_ {
+    let offsets = self.topology.offsets((x, y));
+    let wraps = self.topology.wraps();
+    let width = self.width as isize;
+    let height = self.height as isize;
+    // Custom offsets can be arbitrarily large, overflowing counts as off the
+    // board
+    let neighbor = move |&(dx, dy): &Offset| {
+      if wraps {
+        let i = (x as isize + dx.rem_euclid(width)) % width;
+        let j = (y as isize + dy.rem_euclid(height)) % height;
 
-    (x.max(1) - 1..=(x + 1).min(width - 1))
-      .flat_map(move |i| {
-        (y.max(1) - 1..=(y + 1).min(height - 1)).map(move |j| (i, j))
-      })
-      .filter(move |&pos| pos != (x, y))
+        return Some((i as usize, j as usize));
+      }
+
+      let i = (x as isize).checked_add(dx)?;
+      let j = (y as isize).checked_add(dy)?;
+
+      ((0..width).contains(&i) && (0..height).contains(&j))
+        .then_some((i as usize, j as usize))
+    };
+
+    offsets.iter().enumerate().filter_map(move |(k, offset)| {
+      let pos = neighbor(offset)?;
+
+      // On small wrapping boards, several offsets can lead to the same field,
+      // and wrapping or zero offsets can lead back to the field itself
+      let duplicate = pos == (x, y)
+        || wraps
+          && offsets[..k]
+            .iter()
+            .any(|other| neighbor(other) == Some(pos));
+
+      (!duplicate).then_some(pos)
+    })
   }
 
   pub fn neighboring_mines(&self, pos: Position) -> u8 {
-    self
+    self.index(pos).map_or(0, |i| self.neighbor_counts[i])
+  }
+
+  pub(crate) fn started_at(&self) -> Option<f64> {
+    self.started_at
+  }
+
+  pub(crate) fn raw_stats(&self) -> Stats {
+    self.stats
+  }
+
+  // Milliseconds since the first action, starting the clock if necessary
+  fn elapsed(&mut self) -> u64 {
+    let now = time::now();
+    let started_at = *self.started_at.get_or_insert(now);
+
+    (now - started_at).max(0.0) as u64
+  }
+
+  // Opens a covered field or chords on an open one, returning all fields
+  // whose visible state changed
+  pub fn open(&mut self, pos: Position) -> ChangeSet {
+    let time = self.elapsed();
+
+    self.open_at(pos, time)
+  }
+
+  // Like `open`, but with an explicit timestamp in milliseconds since the
+  // first action, e.g. when replaying a recorded game
+  pub fn open_at(&mut self, pos: Position, time: u64) -> ChangeSet {
+    if self.status != GameStatus::Playing || self.index(pos).is_none() {
+      return ChangeSet::new(self.status);
+    }
+
+    let mut record = if self.is_open(pos) {
+      self.stats.chord_clicks += 1;
+      ActionRecord::new(Action::Chord(pos), time, self.status)
+    } else {
+      self.stats.left_clicks += 1;
+      ActionRecord::new(Action::Open(pos), time, self.status)
+    };
+
+    match record.action {
+      Action::Chord(_) => self.chord(pos, &mut record),
+      _ => self.reveal(pos, &mut record),
+    }
+
+    record.flagged.extend(self.check_won());
+    self.push_record(record)
+  }
+
+  fn chord(&mut self, pos: Position, record: &mut ActionRecord) {
+    let mine_count = self.neighboring_mines(pos);
+    let flag_count = self
       .iter_neighbors(pos)
-      .filter(|pos| self.mines.contains(pos))
-      .count() as u8
-  }
-
-  pub fn open(&mut self, pos: Position) -> Option<OpenResult> {
-    if self.open_fields.contains(&pos) {
-      let mine_count = self.neighboring_mines(pos);
-      let flag_count = self
-        .iter_neighbors(pos)
-        .filter(|neighbor| self.flagged_fields.contains(neighbor))
-        .count() as u8;
-
-      if mine_count == flag_count {
-        for neighbor in self.iter_neighbors(pos) {
-          if !self.flagged_fields.contains(&neighbor)
-            && !self.open_fields.contains(&neighbor)
-          {
-            self.open(neighbor);
-          }
+      .filter(|&neighbor| self.is_flagged(neighbor))
+      .count() as u8;
+
+    if mine_count == flag_count {
+      let neighbors = self.iter_neighbors(pos).collect::<Vec<_>>();
+
+      for neighbor in neighbors {
+        if !self.is_flagged(neighbor) && !self.is_open(neighbor) {
+          self.reveal(neighbor, record);
         }
       }
+    }
+  }
 
-      return None;
+  fn reveal(&mut self, pos: Position, record: &mut ActionRecord) {
+    let index = match self.index(pos) {
+      Some(index) => index,
+      None => return,
+    };
+
+    if self.status != GameStatus::Playing
+      || self.open_fields.contains(index)
+      || self.flagged_fields.contains(index)
+    {
+      return;
     }
 
-    if self.lost || self.flagged_fields.contains(&pos) {
-      return None;
+    if !self.mines_placed() {
+      self.place_mines_around(pos);
     }
 
-    self.open_fields.insert(pos);
+    if self.mines.contains(index) {
+      if self.lives_left().is_some_and(|lives| lives > 1) {
+        self.detonated.insert(index);
+        self.flagged_fields.insert(index);
+        record.detonated.push(pos);
+        record.flagged.push(pos);
+      } else {
+        self.open_fields.insert(index);
+        record.opened.push(pos);
+        self.status = GameStatus::Lost;
+      }
+    } else {
+      record.opened.extend(self.flood_fill(pos));
+    }
+  }
 
-    let is_mine = self.mines.contains(&pos);
+  // Opens the given field and keeps opening the neighbors of opened fields
+  // without neighboring mines. Uses an explicit queue instead of recursion, so
+  // huge empty areas don't overflow the stack. Returns all opened fields.
+  fn flood_fill(&mut self, start: Position) -> Vec<Position> {
+    let mut opened = vec![];
+    let mut queue = VecDeque::from([start]);
+    let mut neighbors = vec![];
 
-    if is_mine {
-      self.lost = true;
-      Some(OpenResult::Mine)
-    } else {
-      let mine_count = self.neighboring_mines(pos);
+    self.open_fields.insert(self.unchecked_index(start));
 
-      if mine_count == 0 {
-        for neighbor in self.iter_neighbors(pos) {
-          if !self.open_fields.contains(&neighbor) {
-            self.open(neighbor);
-          }
+    while let Some(pos) = queue.pop_front() {
+      opened.push(pos);
+
+      if self.neighboring_mines(pos) > 0 {
+        continue;
+      }
+
+      neighbors.clear();
+      neighbors.extend(self.iter_neighbors(pos));
+
+      for &neighbor in &neighbors {
+        let index = self.unchecked_index(neighbor);
+
+        if !self.open_fields.contains(index)
+          && !self.flagged_fields.contains(index)
+        {
+          self.open_fields.insert(index);
+          queue.push_back(neighbor);
         }
       }
+    }
 
-      Some(OpenResult::NoMine(mine_count))
+    opened
+  }
+
+  // Returns the mines that got flagged automatically on winning
+  fn check_won(&mut self) -> Vec<Position> {
+    let mut flagged = vec![];
+
+    if self.status == GameStatus::Playing
+      && self.open_fields.len() + self.mines.len() == self.width * self.height
+    {
+      self.status = GameStatus::Won;
+
+      for i in self.mines.iter() {
+        if self.flagged_fields.insert(i) {
+          flagged.push(self.position(i));
+        }
+      }
     }
+
+    flagged
   }
 
-  pub fn toggle_flag(&mut self, pos: Position) {
-    if self.lost || self.open_fields.contains(&pos) {
-      return;
+  pub fn toggle_flag(&mut self, pos: Position) -> ChangeSet {
+    let time = self.elapsed();
+
+    self.toggle_flag_at(pos, time)
+  }
+
+  pub fn toggle_flag_at(&mut self, pos: Position, time: u64) -> ChangeSet {
+    let index = match self.index(pos) {
+      Some(index) => index,
+      None => return ChangeSet::new(self.status),
+    };
+
+    if self.status != GameStatus::Playing {
+      return ChangeSet::new(self.status);
     }
 
-    if self.flagged_fields.contains(&pos) {
-      self.flagged_fields.remove(&pos);
+    self.stats.right_clicks += 1;
+
+    if self.open_fields.contains(index) || self.detonated.contains(index) {
+      return ChangeSet::new(self.status);
+    }
+
+    let mut record =
+      ActionRecord::new(Action::ToggleFlag(pos), time, self.status);
+
+    if self.flagged_fields.remove(index) {
+      record.unflagged.push(pos);
+
+      if self.flag_cycle == FlagCycle::FlagAndQuestion {
+        self.marked_fields.insert(index);
+        record.marked.push(pos);
+      }
+    } else if self.is_marked(pos) {
+      self.marked_fields.remove(index);
+      record.unmarked.push(pos);
     } else {
-      self.flagged_fields.insert(pos);
+      self.flagged_fields.insert(index);
+      record.flagged.push(pos);
+    }
+
+    self.push_record(record)
+  }
+
+  fn push_record(&mut self, mut record: ActionRecord) -> ChangeSet {
+    record.status_after = self.status;
+
+    if record.status_after != record.status_before {
+      self.stats.time = record.time;
+    }
+
+    let changes = self.changes(&record);
+
+    if !record.is_empty() {
+      self.history.push(record);
+      self.redo_stack.clear();
     }
+
+    changes
+  }
+
+  pub fn history(&self) -> &[ActionRecord] {
+    &self.history
+  }
+
+  pub fn can_undo(&self) -> bool {
+    !self.history.is_empty()
+  }
+
+  pub fn can_redo(&self) -> bool {
+    !self.redo_stack.is_empty()
+  }
+
+  // Whether an action was ever undone, even if it was redone afterwards
+  pub fn undo_used(&self) -> bool {
+    self.undo_used
+  }
+
+  // Reverts the last action. The mine layout is kept, even when undoing the
+  // first click.
+  pub fn undo(&mut self) -> bool {
+    let record = match self.history.pop() {
+      Some(record) => record,
+      None => return false,
+    };
+
+    for &pos in &record.opened {
+      self.open_fields.remove(self.unchecked_index(pos));
+    }
+
+    for &pos in &record.flagged {
+      self.flagged_fields.remove(self.unchecked_index(pos));
+    }
+
+    for &pos in &record.unflagged {
+      self.flagged_fields.insert(self.unchecked_index(pos));
+    }
+
+    for &pos in &record.marked {
+      self.marked_fields.remove(self.unchecked_index(pos));
+    }
+
+    for &pos in &record.unmarked {
+      self.marked_fields.insert(self.unchecked_index(pos));
+    }
+
+    for &pos in &record.detonated {
+      self.detonated.remove(self.unchecked_index(pos));
+    }
+
+    self.status = record.status_before;
+    self.redo_stack.push(record);
+    self.undo_used = true;
+
+    true
+  }
+
+  pub fn redo(&mut self) -> bool {
+    let record = match self.redo_stack.pop() {
+      Some(record) => record,
+      None => return false,
+    };
+
+    for &pos in &record.opened {
+      self.open_fields.insert(self.unchecked_index(pos));
+    }
+
+    for &pos in &record.flagged {
+      self.flagged_fields.insert(self.unchecked_index(pos));
+    }
+
+    for &pos in &record.unflagged {
+      self.flagged_fields.remove(self.unchecked_index(pos));
+    }
+
+    for &pos in &record.marked {
+      self.marked_fields.insert(self.unchecked_index(pos));
+    }
+
+    for &pos in &record.unmarked {
+      self.marked_fields.remove(self.unchecked_index(pos));
+    }
+
+    for &pos in &record.detonated {
+      self.detonated.insert(self.unchecked_index(pos));
+    }
+
+    self.status = record.status_after;
+    self.stats.time = record.time;
+    self.history.push(record);
+
+    true
   }
 }
 
 #[cfg(test)]
 mod tests {
-  use crate::Minesweeper;
+  use crate::{
+    snapshot::FieldState, solver, topology::Custom, Difficulty, FirstClick,
+    FlagCycle, GameOptions, GameStatus, Generation, Minesweeper,
+    MinesweeperError,
+  };
 
   #[test]
   fn test() {
@@ -169,4 +1174,327 @@ mod tests {
 
     println!("{}", ms);
   }
+
+  #[test]
+  fn same_seed_same_board() {
+    let mut a = Minesweeper::with_seed(16, 16, 40, 1234);
+    let mut b = Minesweeper::with_seed(16, 16, 40, 1234);
+    a.open((3, 3));
+    b.open((3, 3));
+
+    assert_eq!(a.mines, b.mines);
+    assert_eq!(a.seed(), Some(1234));
+  }
+
+  #[test]
+  fn mines_are_placed_on_first_click() {
+    let mut ms = Minesweeper::with_seed(9, 9, 10, 5);
+    assert!(!ms.mines_placed());
+
+    ms.open((4, 4));
+    assert!(matches!(ms.field_state((4, 4)), FieldState::Open(_)));
+    assert!(ms.mines_placed());
+    assert_eq!(ms.mines.len(), 10);
+  }
+
+  #[test]
+  fn first_click_is_safe() {
+    for seed in 0..50 {
+      let mut ms = Minesweeper::with_options(
+        5,
+        5,
+        15,
+        GameOptions {
+          seed: Some(seed),
+          first_click: FirstClick::SafeNeighborhood,
+          ..GameOptions::default()
+        },
+      );
+
+      ms.open((0, 0));
+      assert_eq!(ms.field_state((0, 0)), FieldState::Open(0));
+    }
+  }
+
+  #[test]
+  fn unprotected_places_mines_immediately() {
+    let ms = Minesweeper::with_options(
+      9,
+      9,
+      10,
+      GameOptions {
+        seed: Some(5),
+        first_click: FirstClick::Unprotected,
+        ..GameOptions::default()
+      },
+    );
+
+    assert!(ms.mines_placed());
+    assert_eq!(ms.mines.len(), 10);
+  }
+
+  #[test]
+  fn no_guess_generation() {
+    for seed in 0..10 {
+      let options = GameOptions {
+        seed: Some(seed),
+        first_click: FirstClick::SafeNeighborhood,
+        generation: Generation::NoGuess { max_attempts: 200 },
+        ..GameOptions::default()
+      };
+      let mut ms = Minesweeper::with_options(9, 9, 10, options);
+      assert_eq!(ms.guess_free(), None);
+
+      ms.open((4, 4));
+      assert_eq!(ms.guess_free(), Some(true));
+      assert!(solver::solve_to_end(&mut ms));
+    }
+  }
+
+  #[test]
+  fn win_flags_remaining_mines() {
+    let mut ms = Minesweeper::with_mines(3, 1, [(2, 0)]);
+    ms.open((0, 0));
+
+    assert_eq!(ms.status(), GameStatus::Won);
+    assert!(ms.is_flagged((2, 0)));
+  }
+
+  #[test]
+  fn lose_on_mine() {
+    let mut ms = Minesweeper::with_mines(3, 1, [(2, 0)]);
+    ms.open((2, 0));
+
+    assert_eq!(ms.status(), GameStatus::Lost);
+    assert!(ms.open((0, 0)).is_empty());
+  }
+
+  #[test]
+  fn invalid_construction() {
+    assert_eq!(
+      Minesweeper::try_with_options(0, 5, 0, GameOptions::default())
+        .unwrap_err(),
+      MinesweeperError::InvalidDimensions {
+        width: 0,
+        height: 5
+      }
+    );
+    assert_eq!(
+      Minesweeper::try_with_options(3, 3, 9, GameOptions::default())
+        .unwrap_err(),
+      MinesweeperError::TooManyMines {
+        mine_count: 9,
+        field_count: 9
+      }
+    );
+
+    let options = GameOptions {
+      first_click: FirstClick::SafeNeighborhood,
+      ..GameOptions::default()
+    };
+
+    assert_eq!(
+      Minesweeper::try_with_options(4, 4, 8, options).unwrap_err(),
+      MinesweeperError::TooFewSafeFields {
+        needed: 9,
+        available: 8
+      }
+    );
+
+    // A full board still keeps the whole neighborhood free
+    let mut ms = Minesweeper::try_with_options(4, 4, 7, options).unwrap();
+    ms.open((1, 1));
+
+    assert_eq!(ms.neighboring_mines((1, 1)), 0);
+    assert!(ms.iter_neighbors((1, 1)).all(|pos| ms.is_open(pos)));
+  }
+
+  #[test]
+  fn difficulty_presets() {
+    let ms =
+      Minesweeper::with_difficulty(Difficulty::Expert, GameOptions::default());
+
+    assert_eq!((ms.width(), ms.height(), ms.mine_count()), (30, 16, 99));
+    assert_eq!(
+      Difficulty::from_name("beginner"),
+      Some(Difficulty::Beginner)
+    );
+  }
+
+  #[test]
+  fn huge_empty_board() {
+    let mut ms = Minesweeper::with_mines(1000, 1000, [(999, 999)]);
+    ms.open((0, 0));
+
+    assert_eq!(ms.status(), GameStatus::Won);
+    assert_eq!(ms.history()[0].opened.len(), 1000 * 1000 - 1);
+  }
+
+  #[test]
+  fn huge_sparse_board() {
+    let mut ms = Minesweeper::with_seed(1000, 1000, 50, 3);
+    ms.open((500, 500));
+
+    let opened = &ms.history()[0].opened;
+
+    // With so few mines, a single click clears the whole board
+    assert_eq!(ms.status(), GameStatus::Won);
+    assert_eq!(opened.len(), 1000 * 1000 - 50);
+    assert!(opened
+      .iter()
+      .all(|&pos| ms.is_open(pos) && !ms.is_mine(pos)));
+    assert_eq!(
+      ms.iter_positions().filter(|&pos| ms.is_open(pos)).count(),
+      opened.len()
+    );
+  }
+
+  #[test]
+  fn huge_chord() {
+    let mut ms = Minesweeper::with_mines(1000, 1000, [(0, 0)]);
+    ms.open((1, 0));
+    ms.toggle_flag((0, 0));
+
+    assert_eq!(ms.history()[0].opened, [(1, 0)]);
+
+    ms.open((1, 0));
+
+    assert_eq!(ms.status(), GameStatus::Won);
+    assert_eq!(ms.history()[2].opened.len(), 1000 * 1000 - 2);
+  }
+
+  #[test]
+  fn asymmetric_neighbors() {
+    // Every field only sees the field to its right
+    let right = Custom {
+      name: "right".to_string(),
+      offsets: vec![(1, 0)],
+      wraps: false,
+    };
+    let ms =
+      Minesweeper::with_topology_and_mines(right.clone(), 3, 1, [(0, 0)]);
+
+    assert_eq!(ms.neighboring_mines((1, 0)), 0);
+    assert_eq!(ms.neighboring_mines((2, 0)), 0);
+
+    let ms = Minesweeper::with_topology_and_mines(right, 3, 1, [(2, 0)]);
+
+    assert_eq!(ms.neighboring_mines((0, 0)), 0);
+    assert_eq!(ms.neighboring_mines((1, 0)), 1);
+  }
+
+  #[test]
+  fn outside_fields_are_ignored() {
+    let mut ms = Minesweeper::with_mines(3, 2, [(1, 0), (5, 5)]);
+
+    assert_eq!(ms.mine_count(), 1);
+    assert!(ms.open((3, 0)).is_empty());
+    ms.toggle_flag((0, 2));
+
+    assert!(!ms.is_open((3, 0)));
+    assert!(!ms.is_flagged((0, 2)));
+    assert!(ms.history().is_empty());
+    assert_eq!(ms.neighboring_mines((0, 1)), 1);
+  }
+
+  #[test]
+  fn question_marks() {
+    let mut ms = Minesweeper::with_mines(3, 2, [(1, 0)]);
+    ms.set_flag_cycle(FlagCycle::FlagAndQuestion);
+    ms.open((0, 0));
+    ms.toggle_flag((1, 0));
+    ms.toggle_flag((1, 0));
+
+    assert!(!ms.is_flagged((1, 0)));
+    assert!(ms.is_marked((1, 0)));
+
+    // Question marks don't count as flags when chording
+    ms.open((0, 0));
+    assert!(!ms.is_open((0, 1)));
+
+    ms.toggle_flag((1, 0));
+    assert!(!ms.is_marked((1, 0)));
+
+    ms.undo();
+    assert!(ms.is_marked((1, 0)));
+    ms.undo();
+    assert!(ms.is_flagged((1, 0)));
+    assert!(!ms.is_marked((1, 0)));
+    ms.redo();
+    assert!(ms.is_marked((1, 0)));
+
+    // Marked fields can still be opened
+    ms.toggle_flag((2, 1));
+    ms.toggle_flag((2, 1));
+    ms.open((2, 1));
+    assert!(ms.is_open((2, 1)));
+    assert!(!ms.is_marked((2, 1)));
+  }
+
+  #[test]
+  fn lives_mode() {
+    let mut ms = Minesweeper::with_mines(4, 1, [(0, 0), (3, 0)]);
+    ms.set_lives(Some(2));
+    ms.open((0, 0));
+
+    assert_eq!(ms.status(), GameStatus::Playing);
+    assert_eq!(ms.lives_left(), Some(1));
+    assert!(ms.is_detonated((0, 0)));
+    assert!(ms.is_flagged((0, 0)));
+
+    // Detonated mines can't be unflagged
+    ms.toggle_flag((0, 0));
+    assert!(ms.is_flagged((0, 0)));
+
+    ms.undo();
+    assert_eq!(ms.lives_left(), Some(2));
+    assert!(!ms.is_flagged((0, 0)));
+    ms.redo();
+
+    ms.open((3, 0));
+    assert_eq!(ms.status(), GameStatus::Lost);
+    assert_eq!(ms.lives_left(), Some(0));
+    assert_eq!(Minesweeper::new(9, 9, 10).lives_left(), None);
+  }
+
+  #[test]
+  fn undo_and_redo() {
+    let mut ms = Minesweeper::with_mines(4, 2, [(2, 0), (3, 1)]);
+    ms.toggle_flag((3, 1));
+    ms.open((0, 0));
+    ms.open((2, 0));
+
+    assert_eq!(ms.status(), GameStatus::Lost);
+    assert!(ms.undo());
+    assert_eq!(ms.status(), GameStatus::Playing);
+    assert!(!ms.is_open((2, 0)));
+    assert!(ms.is_open((1, 1)));
+
+    assert!(ms.undo());
+    assert!(!ms.is_open((0, 0)));
+    assert!(!ms.is_open((1, 1)));
+    assert!(ms.is_flagged((3, 1)));
+
+    assert!(ms.undo());
+    assert!(!ms.is_flagged((3, 1)));
+    assert!(!ms.undo());
+
+    assert!(ms.redo());
+    assert!(ms.redo());
+    assert!(ms.is_open((1, 1)));
+    assert!(ms.redo());
+    assert_eq!(ms.status(), GameStatus::Lost);
+    assert!(!ms.redo());
+  }
+
+  #[test]
+  fn new_action_clears_redo() {
+    let mut ms = Minesweeper::with_mines(4, 2, [(2, 0), (3, 1)]);
+    ms.open((0, 0));
+    ms.undo();
+    ms.toggle_flag((3, 1));
+
+    assert!(!ms.can_redo());
+    assert_eq!(ms.history().len(), 1);
+  }
 }
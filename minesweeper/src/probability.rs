@@ -0,0 +1,589 @@
+use crate::{
+  minesweeper::{Minesweeper, Position},
+  topology::Topology,
+};
+use std::collections::{HashMap, HashSet};
+
+// Upper bound on the search steps `mine_probabilities` spends enumerating
+// frontier layouts and combining the counts of their components. Normal play
+// needs far less, but long frontiers of scattered open fields grow
+// exponentially and many components grow quadratically.
+pub const MAX_STEPS: usize = 1 << 20;
+
+struct Constraint {
+  fields: Vec<usize>,
+  mines: usize,
+}
+
+// A group of frontier fields that share no constraint with fields outside of
+// the group, so its mines can be enumerated independently
+struct Component {
+  fields: Vec<Position>,
+  constraints: Vec<Constraint>,
+}
+
+struct Enumeration {
+  // Number of valid assignments by amount of mines used
+  counts: Vec<f64>,
+  // Number of valid assignments with a mine on each field by amount of mines
+  field_counts: Vec<Vec<f64>>,
+}
+
+// Partial layout of the backtracking search
+struct Search<'a> {
+  field_constraints: Vec<Vec<usize>>,
+  assignment: Vec<bool>,
+  // Mines and unassigned fields per constraint
+  placed: Vec<usize>,
+  unassigned: Vec<usize>,
+  budget: &'a mut usize,
+}
+
+impl Component {
+  // Returns `None` once the search takes more than `budget` steps
+  fn enumerate(&self, budget: &mut usize) -> Option<Enumeration> {
+    let size = self.fields.len();
+    let mut result = Enumeration {
+      counts: vec![0.0; size + 1],
+      field_counts: vec![vec![0.0; size]; size + 1],
+    };
+    let mut search = Search {
+      field_constraints: vec![vec![]; size],
+      assignment: vec![false; size],
+      placed: vec![0; self.constraints.len()],
+      unassigned: self
+        .constraints
+        .iter()
+        .map(|constraint| constraint.fields.len())
+        .collect(),
+      budget,
+    };
+
+    for (i, constraint) in self.constraints.iter().enumerate() {
+      for &field in &constraint.fields {
+        search.field_constraints[field].push(i);
+      }
+    }
+
+    self
+      .backtrack(0, &mut search, &mut result)
+      .then_some(result)
+  }
+
+  fn backtrack(
+    &self,
+    index: usize,
+    search: &mut Search,
+    result: &mut Enumeration,
+  ) -> bool {
+    if *search.budget == 0 {
+      return false;
+    }
+
+    *search.budget -= 1;
+
+    if index == search.assignment.len() {
+      let mine_count = search.assignment.iter().filter(|&&mine| mine).count();
+
+      result.counts[mine_count] += 1.0;
+
+      for (i, &mine) in search.assignment.iter().enumerate() {
+        if mine {
+          result.field_counts[mine_count][i] += 1.0;
+        }
+      }
+
+      return true;
+    }
+
+    for mine in [false, true] {
+      let mut valid = true;
+
+      for &i in &search.field_constraints[index] {
+        search.unassigned[i] -= 1;
+        search.placed[i] += mine as usize;
+
+        let needed = self.constraints[i].mines;
+
+        if search.placed[i] > needed
+          || search.placed[i] + search.unassigned[i] < needed
+        {
+          valid = false;
+        }
+      }
+
+      let finished = !valid || {
+        search.assignment[index] = mine;
+
+        let finished = self.backtrack(index + 1, search, result);
+
+        search.assignment[index] = false;
+        finished
+      };
+
+      for &i in &search.field_constraints[index] {
+        search.unassigned[i] += 1;
+        search.placed[i] -= mine as usize;
+      }
+
+      if !finished {
+        return false;
+      }
+    }
+
+    true
+  }
+}
+
+// Counts one step per multiplication, returning `None` if that exceeds the
+// budget
+fn convolve(a: &[f64], b: &[f64], budget: &mut usize) -> Option<Vec<f64>> {
+  *budget = budget.checked_sub(a.len() * b.len())?;
+
+  let mut result = vec![0.0; a.len() + b.len() - 1];
+
+  for (i, &x) in a.iter().enumerate() {
+    for (j, &y) in b.iter().enumerate() {
+      result[i + j] += x * y;
+    }
+  }
+
+  Some(result)
+}
+
+fn ln_factorials(n: usize) -> Vec<f64> {
+  let mut result = vec![0.0; n + 1];
+
+  for i in 1..=n {
+    result[i] = result[i - 1] + (i as f64).ln();
+  }
+
+  result
+}
+
+impl<T: Topology> Minesweeper<T> {
+  // Computes the probability of each covered, unflagged field being a mine
+  // given everything the player can see. Flags are trusted to be correct.
+  // Returns an empty map if the game is lost or the visible state is
+  // contradictory. The result is exact unless the search takes more than
+  // `MAX_STEPS`, in which case it falls back to `approximate_probabilities`.
+  pub fn mine_probabilities(&self) -> HashMap<Position, f64> {
+    self
+      .try_mine_probabilities(MAX_STEPS)
+      .unwrap_or_else(|| self.approximate_probabilities())
+  }
+
+  // Exact probabilities, or `None` if enumerating the frontier and combining
+  // its components takes more than `max_steps` steps
+  pub fn try_mine_probabilities(
+    &self,
+    max_steps: usize,
+  ) -> Option<HashMap<Position, f64>> {
+    if self.is_lost() {
+      return Some(HashMap::new());
+    }
+
+    let is_unknown =
+      |pos: Position| !self.is_open(pos) && !self.is_flagged(pos);
+    let unknown_fields = self
+      .iter_positions()
+      .filter(|&pos| is_unknown(pos))
+      .collect::<Vec<_>>();
+    let flag_count = self
+      .iter_positions()
+      .filter(|&pos| self.is_flagged(pos))
+      .count();
+    let components = self.frontier_components();
+    let frontier = components
+      .iter()
+      .flat_map(|component| component.fields.iter().copied())
+      .collect::<HashSet<_>>();
+    let interior_count = unknown_fields.len() - frontier.len();
+    let remaining_mines = match self.mine_count().checked_sub(flag_count) {
+      Some(remaining_mines) => remaining_mines,
+      None => return Some(HashMap::new()),
+    };
+
+    let mut budget = max_steps;
+    let enumerations = components
+      .iter()
+      .map(|component| component.enumerate(&mut budget))
+      .collect::<Option<Vec<_>>>()?;
+
+    // Weight of placing `k` of the remaining mines in the interior, scaled to
+    // avoid overflowing on large boards
+    let ln_factorials = ln_factorials(interior_count);
+    let ln_binomial = |k: usize| {
+      ln_factorials[interior_count]
+        - ln_factorials[k]
+        - ln_factorials[interior_count - k]
+    };
+    let max_ln_binomial = (remaining_mines.saturating_sub(frontier.len())
+      ..=remaining_mines.min(interior_count))
+      .map(ln_binomial)
+      .fold(f64::MIN, f64::max);
+    let interior_weight = |frontier_mines: usize| {
+      remaining_mines
+        .checked_sub(frontier_mines)
+        .filter(|&k| k <= interior_count)
+        .map(|k| (ln_binomial(k) - max_ln_binomial).exp())
+        .unwrap_or(0.0)
+    };
+
+    // Counts of the components before and after each component, so that
+    // the counts of all other components take a single convolution
+    let mut prefix_counts = vec![vec![1.0]];
+    let mut suffix_counts = vec![vec![1.0]];
+
+    for (front, back) in enumerations.iter().zip(enumerations.iter().rev()) {
+      let prefix = convolve(
+        &prefix_counts[prefix_counts.len() - 1],
+        &front.counts,
+        &mut budget,
+      )?;
+      let suffix = convolve(
+        &back.counts,
+        &suffix_counts[suffix_counts.len() - 1],
+        &mut budget,
+      )?;
+
+      prefix_counts.push(prefix);
+      suffix_counts.push(suffix);
+    }
+
+    suffix_counts.reverse();
+
+    let total_counts = &prefix_counts[enumerations.len()];
+    let total_weight = total_counts
+      .iter()
+      .enumerate()
+      .map(|(k, &count)| count * interior_weight(k))
+      .sum::<f64>();
+
+    if total_weight <= 0.0 {
+      return Some(HashMap::new());
+    }
+
+    let mut result = HashMap::new();
+
+    for (c, (component, enumeration)) in
+      components.iter().zip(&enumerations).enumerate()
+    {
+      let other_counts =
+        convolve(&prefix_counts[c], &suffix_counts[c + 1], &mut budget)?;
+
+      budget =
+        budget.checked_sub(enumeration.counts.len() * other_counts.len())?;
+
+      // Weight of all configurations outside of this component, given that
+      // the component itself holds `k` mines
+      let outside_weights = (0..enumeration.counts.len())
+        .map(|k| {
+          other_counts
+            .iter()
+            .enumerate()
+            .map(|(j, &count)| count * interior_weight(k + j))
+            .sum::<f64>()
+        })
+        .collect::<Vec<_>>();
+
+      for (i, &pos) in component.fields.iter().enumerate() {
+        let weight = enumeration
+          .field_counts
+          .iter()
+          .zip(&outside_weights)
+          .map(|(field_counts, &outside_weight)| {
+            field_counts[i] * outside_weight
+          })
+          .sum::<f64>();
+
+        result.insert(pos, weight / total_weight);
+      }
+    }
+
+    if interior_count > 0 {
+      let expected_interior_mines = total_counts
+        .iter()
+        .enumerate()
+        .filter(|&(k, _)| k <= remaining_mines)
+        .map(|(k, &count)| {
+          count * interior_weight(k) * (remaining_mines - k) as f64
+        })
+        .sum::<f64>()
+        / total_weight;
+      let probability = expected_interior_mines / interior_count as f64;
+
+      for pos in unknown_fields {
+        if !frontier.contains(&pos) {
+          result.insert(pos, probability);
+        }
+      }
+    }
+
+    Some(result)
+  }
+
+  // A cheap estimate that looks at each constraint on its own: frontier
+  // fields get the highest density of missing mines among their open
+  // neighbors, all other fields the density of the mines left over
+  pub fn approximate_probabilities(&self) -> HashMap<Position, f64> {
+    if self.is_lost() {
+      return HashMap::new();
+    }
+
+    let is_unknown =
+      |pos: Position| !self.is_open(pos) && !self.is_flagged(pos);
+    let mut result = HashMap::<Position, f64>::new();
+
+    for pos in self.iter_positions().filter(|&pos| self.is_open(pos)) {
+      let fields = self
+        .iter_neighbors(pos)
+        .filter(|&neighbor| is_unknown(neighbor))
+        .collect::<Vec<_>>();
+      let flag_count = self
+        .iter_neighbors(pos)
+        .filter(|&neighbor| self.is_flagged(neighbor))
+        .count();
+      let missing =
+        (self.neighboring_mines(pos) as usize).saturating_sub(flag_count);
+
+      for field in &fields {
+        let density = (missing as f64 / fields.len() as f64).min(1.0);
+        let probability = result.entry(*field).or_insert(0.0);
+
+        *probability = probability.max(density);
+      }
+    }
+
+    let flag_count = self
+      .iter_positions()
+      .filter(|&pos| self.is_flagged(pos))
+      .count();
+    let unknown_count =
+      self.iter_positions().filter(|&pos| is_unknown(pos)).count();
+    let density = if unknown_count > 0 {
+      (self.mine_count().saturating_sub(flag_count) as f64
+        / unknown_count as f64)
+        .min(1.0)
+    } else {
+      0.0
+    };
+
+    for pos in self.iter_positions().filter(|&pos| is_unknown(pos)) {
+      result.entry(pos).or_insert(density);
+    }
+
+    result
+  }
+
+  fn frontier_components(&self) -> Vec<Component> {
+    let is_unknown =
+      |pos: Position| !self.is_open(pos) && !self.is_flagged(pos);
+    let mut constraints = vec![];
+
+    for pos in self.iter_positions().filter(|&pos| self.is_open(pos)) {
+      let fields = self
+        .iter_neighbors(pos)
+        .filter(|&neighbor| is_unknown(neighbor))
+        .collect::<Vec<_>>();
+
+      if fields.is_empty() {
+        continue;
+      }
+
+      let flag_count = self
+        .iter_neighbors(pos)
+        .filter(|&neighbor| self.is_flagged(neighbor))
+        .count();
+
+      constraints.push((
+        fields,
+        (self.neighboring_mines(pos) as usize).saturating_sub(flag_count),
+      ));
+    }
+
+    let mut field_constraints = HashMap::<Position, Vec<usize>>::new();
+
+    for (i, (fields, _)) in constraints.iter().enumerate() {
+      for &pos in fields {
+        field_constraints.entry(pos).or_default().push(i);
+      }
+    }
+
+    let mut visited = vec![false; constraints.len()];
+    let mut components = vec![];
+
+    for start in 0..constraints.len() {
+      if visited[start] {
+        continue;
+      }
+
+      let mut stack = vec![start];
+      let mut constraint_indices = vec![];
+      let mut fields = vec![];
+      let mut field_indices = HashMap::new();
+
+      visited[start] = true;
+
+      while let Some(i) = stack.pop() {
+        constraint_indices.push(i);
+
+        for &pos in &constraints[i].0 {
+          if field_indices.contains_key(&pos) {
+            continue;
+          }
+
+          field_indices.insert(pos, fields.len());
+          fields.push(pos);
+
+          for &j in &field_constraints[&pos] {
+            if !visited[j] {
+              visited[j] = true;
+              stack.push(j);
+            }
+          }
+        }
+      }
+
+      components.push(Component {
+        constraints: constraint_indices
+          .into_iter()
+          .map(|i| Constraint {
+            fields: constraints[i]
+              .0
+              .iter()
+              .map(|pos| field_indices[pos])
+              .collect(),
+            mines: constraints[i].1,
+          })
+          .collect(),
+        fields,
+      });
+    }
+
+    components
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{FirstClick, GameOptions, Minesweeper};
+  use std::collections::HashMap;
+  use std::time::{Duration, Instant};
+
+  fn brute_force(ms: &Minesweeper) -> HashMap<(usize, usize), f64> {
+    let positions = ms.iter_positions().collect::<Vec<_>>();
+    let mut counts = HashMap::new();
+    let mut total = 0.0;
+
+    for bits in 0u32..1 << positions.len() {
+      if bits.count_ones() as usize != ms.mine_count() {
+        continue;
+      }
+
+      let layout = positions
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| bits & (1 << i) != 0)
+        .map(|(_, &pos)| pos)
+        .collect::<Vec<_>>();
+      let candidate = Minesweeper::with_mines(ms.width(), ms.height(), layout);
+      let consistent = positions.iter().all(|&pos| {
+        !ms.is_open(pos)
+          || !candidate.is_mine(pos)
+            && candidate.neighboring_mines(pos) == ms.neighboring_mines(pos)
+      });
+
+      if consistent {
+        total += 1.0;
+
+        for &pos in &positions {
+          if candidate.is_mine(pos) {
+            *counts.entry(pos).or_insert(0.0) += 1.0;
+          }
+        }
+      }
+    }
+
+    positions
+      .into_iter()
+      .filter(|&pos| !ms.is_open(pos))
+      .map(|pos| (pos, counts.get(&pos).unwrap_or(&0.0) / total))
+      .collect()
+  }
+
+  #[test]
+  fn matches_brute_force() {
+    for seed in 0..20 {
+      let mut ms = Minesweeper::with_seed(4, 4, 4, seed);
+      ms.open((0, 0));
+
+      let expected = brute_force(&ms);
+      let actual = ms.mine_probabilities();
+
+      assert_eq!(expected.len(), actual.len());
+
+      for (pos, probability) in expected {
+        assert!((actual[&pos] - probability).abs() < 1e-9);
+      }
+    }
+  }
+
+  #[test]
+  fn huge_frontier_falls_back() {
+    // Scattered open fields on an expert board split the frontier into few,
+    // very large components
+    let mut ms = Minesweeper::with_seed(30, 16, 99, 1);
+    ms.open((0, 0));
+
+    for pos in ms.iter_positions().collect::<Vec<_>>() {
+      if (pos.0 + 2 * pos.1) % 4 == 0 && !ms.is_mine(pos) {
+        ms.open(pos);
+      }
+    }
+
+    assert!(ms.try_mine_probabilities(10_000).is_none());
+
+    let probabilities = ms.mine_probabilities();
+
+    assert!(!probabilities.is_empty());
+    assert!(probabilities.values().all(|p| (0.0..=1.0).contains(p)));
+  }
+
+  #[test]
+  fn many_components_finish_quickly() {
+    // A single opening on a large sparse board leaves thousands of small
+    // components along its edge
+    let mut ms = Minesweeper::with_options(
+      400,
+      400,
+      4800,
+      GameOptions {
+        seed: Some(2),
+        first_click: FirstClick::SafeNeighborhood,
+        ..GameOptions::default()
+      },
+    );
+    ms.open((200, 200));
+
+    let start = Instant::now();
+    let probabilities = ms.mine_probabilities();
+
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(!probabilities.is_empty());
+  }
+
+  #[test]
+  fn fifty_fifty() {
+    // 1 ?
+    // 1 ?
+    let mut ms = Minesweeper::with_mines(2, 2, [(1, 0)]);
+    ms.open((0, 0));
+    ms.open((0, 1));
+
+    let probabilities = ms.mine_probabilities();
+
+    assert!((probabilities[&(1, 0)] - 0.5).abs() < 1e-9);
+    assert!((probabilities[&(1, 1)] - 0.5).abs() < 1e-9);
+  }
+}
@@ -1,12 +1,44 @@
 #[cfg(not(target_family = "wasm"))]
 use rand::{thread_rng, Rng};
+use std::fmt::Debug;
 use wasm_bindgen::prelude::*;
 
-#[cfg(not(target_family = "wasm"))]
-pub fn random_range(min: usize, max: usize) -> usize {
-  let mut rng = thread_rng();
+pub trait RandomSource: Debug {
+  fn next_u64(&mut self) -> u64;
+
+  fn range(&mut self, min: usize, max: usize) -> usize {
+    // Multiply-shift instead of modulo avoids bias and is platform independent
+    let len = (max - min) as u128;
+
+    ((self.next_u64() as u128 * len) >> 64) as usize + min
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct SplitMix64 {
+  state: u64,
+}
 
-  rng.gen_range(min..max)
+impl SplitMix64 {
+  pub fn new(seed: u64) -> Self {
+    Self { state: seed }
+  }
+}
+
+impl RandomSource for SplitMix64 {
+  fn next_u64(&mut self) -> u64 {
+    self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+
+    let mut z = self.state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+  }
+}
+
+#[cfg(not(target_family = "wasm"))]
+pub fn random_seed() -> u64 {
+  thread_rng().gen()
 }
 
 #[wasm_bindgen]
@@ -16,6 +48,35 @@ extern "C" {
 }
 
 #[cfg(target_family = "wasm")]
-pub fn random_range(min: usize, max: usize) -> usize {
-  (random() * (max - min) as f64).floor() as usize + min
+pub fn random_seed() -> u64 {
+  // Math.random only yields 52 bits of randomness, so combine two draws
+  let high = (random() * (1u64 << 32) as f64) as u64;
+  let low = (random() * (1u64 << 32) as f64) as u64;
+
+  high << 32 | low
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{RandomSource, SplitMix64};
+
+  #[test]
+  fn same_seed_same_sequence() {
+    let mut a = SplitMix64::new(42);
+    let mut b = SplitMix64::new(42);
+
+    for _ in 0..100 {
+      assert_eq!(a.range(3, 17), b.range(3, 17));
+    }
+  }
+
+  #[test]
+  fn range_is_bounded() {
+    let mut rng = SplitMix64::new(0);
+
+    for _ in 0..1000 {
+      let n = rng.range(5, 8);
+      assert!((5..8).contains(&n));
+    }
+  }
 }
@@ -0,0 +1,189 @@
+use crate::{
+  minesweeper::Minesweeper,
+  snapshot::{FieldState, Snapshot},
+  topology::Topology,
+};
+use std::fmt::{self, Write};
+
+// Turns boards into text with one line per row. Implementations only decide
+// how a single field looks.
+pub trait BoardRenderer {
+  fn write_field(&self, f: &mut dyn Write, field: FieldState) -> fmt::Result;
+
+  // Written between two fields of the same row
+  fn separator(&self) -> &str {
+    " "
+  }
+
+  fn write_board(&self, f: &mut dyn Write, snapshot: &Snapshot) -> fmt::Result {
+    for y in 0..snapshot.height {
+      for x in 0..snapshot.width {
+        if x > 0 {
+          f.write_str(self.separator())?;
+        }
+
+        self.write_field(f, snapshot.get((x, y)))?;
+      }
+
+      f.write_char('\n')?;
+    }
+
+    Ok(())
+  }
+}
+
+// One of "ascii", "emoji" or "ansi"
+pub fn from_name(name: &str) -> Option<&'static dyn BoardRenderer> {
+  match name {
+    "ascii" => Some(&Ascii),
+    "emoji" => Some(&Emoji),
+    "ansi" => Some(&Ansi),
+    _ => None,
+  }
+}
+
+// Plain characters that line up in any terminal
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ascii;
+
+impl Ascii {
+  pub fn symbol(field: FieldState) -> char {
+    match field {
+      FieldState::Covered => '#',
+      FieldState::Flagged => 'F',
+      FieldState::Marked => '?',
+      FieldState::Open(0) => '.',
+      FieldState::Open(count) => char::from(b'0' + count.min(8)),
+      FieldState::Mine => '*',
+      FieldState::ExplodedMine => '@',
+      FieldState::WrongFlag => 'X',
+    }
+  }
+}
+
+impl BoardRenderer for Ascii {
+  fn write_field(&self, f: &mut dyn Write, field: FieldState) -> fmt::Result {
+    f.write_char(Ascii::symbol(field))
+  }
+}
+
+// The default theme used by `Display`. Numbers are padded with spaces to
+// roughly match the width of the emoji.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Emoji;
+
+impl Emoji {
+  pub fn symbol(field: FieldState) -> &'static str {
+    match field {
+      FieldState::Covered => "🟪",
+      FieldState::Flagged => "🚩",
+      FieldState::Marked => "❓",
+      FieldState::Open(0) => "⬜",
+      FieldState::Open(1) => "1",
+      FieldState::Open(2) => "2",
+      FieldState::Open(3) => "3",
+      FieldState::Open(4) => "4",
+      FieldState::Open(5) => "5",
+      FieldState::Open(6) => "6",
+      FieldState::Open(7) => "7",
+      FieldState::Open(_) => "8",
+      FieldState::Mine => "💣",
+      FieldState::ExplodedMine => "💥",
+      FieldState::WrongFlag => "❌",
+    }
+  }
+}
+
+impl BoardRenderer for Emoji {
+  fn write_field(&self, f: &mut dyn Write, field: FieldState) -> fmt::Result {
+    match field {
+      FieldState::Open(1..) => write!(f, " {} ", Emoji::symbol(field)),
+      _ => write!(f, "{} ", Emoji::symbol(field)),
+    }
+  }
+
+  fn separator(&self) -> &str {
+    ""
+  }
+}
+
+// The ASCII theme with the classic colors as ANSI escape codes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ansi;
+
+impl Ansi {
+  // The parameters of the escape code that colors a field, also used by the
+  // terminal game
+  pub fn style(field: FieldState) -> &'static str {
+    match field {
+      FieldState::Covered => "90",
+      FieldState::Flagged | FieldState::WrongFlag => "1;31",
+      FieldState::Marked => "33",
+      FieldState::Open(0) => "0",
+      FieldState::Open(1) => "94",
+      FieldState::Open(2) => "32",
+      FieldState::Open(3) => "91",
+      FieldState::Open(4) => "34",
+      FieldState::Open(5) => "31",
+      FieldState::Open(6) => "36",
+      FieldState::Open(7) => "35",
+      FieldState::Open(_) => "37",
+      FieldState::Mine => "1",
+      FieldState::ExplodedMine => "1;41",
+    }
+  }
+}
+
+impl BoardRenderer for Ansi {
+  fn write_field(&self, f: &mut dyn Write, field: FieldState) -> fmt::Result {
+    write!(
+      f,
+      "\x1b[{}m{}\x1b[0m",
+      Ansi::style(field),
+      Ascii::symbol(field)
+    )
+  }
+}
+
+impl<T: Topology> Minesweeper<T> {
+  pub fn render(&self, renderer: &dyn BoardRenderer) -> String {
+    let mut out = String::new();
+
+    // Writing to a `String` cannot fail
+    renderer.write_board(&mut out, &self.snapshot()).unwrap();
+    out
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{from_name, Ansi, Ascii, Emoji};
+  use crate::Minesweeper;
+
+  // 1 * 1 . 1 *
+  fn lost_board() -> Minesweeper {
+    let mut ms = Minesweeper::with_mines(6, 1, [(1, 0), (5, 0)]);
+    ms.open((3, 0));
+    ms.toggle_flag((0, 0));
+    ms.open((5, 0));
+    ms
+  }
+
+  #[test]
+  fn themes() {
+    let ms = lost_board();
+
+    assert_eq!(ms.render(&Ascii), "X * 1 . 1 @\n");
+    assert_eq!(ms.render(&Emoji), "❌ 💣  1 ⬜  1 💥 \n");
+    assert_eq!(ms.render(&Emoji), ms.to_string());
+    assert!(ms.render(&Ansi).starts_with("\x1b[1;31mX\x1b[0m \x1b[1m*"));
+  }
+
+  #[test]
+  fn themes_by_name() {
+    let ms = lost_board();
+
+    assert_eq!(ms.render(from_name("ascii").unwrap()), ms.render(&Ascii));
+    assert!(from_name("fancy").is_none());
+  }
+}
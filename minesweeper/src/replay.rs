@@ -0,0 +1,795 @@
+use crate::{
+  minesweeper::{
+    Action, FirstClick, FlagCycle, GameOptions, GameStatus, Generation,
+    Minesweeper, MinesweeperError, Position,
+  },
+  topology::{AnyTopology, Topology},
+};
+use std::{
+  error::Error,
+  fmt::{self, Display},
+  str::FromStr,
+};
+
+// Replays are stored as plain text:
+//
+// ```
+// msreplay 1 <width> <height> <mine count>
+// topology <name>
+// marks question
+// lives <lives>
+// undo used
+// seed <seed> <unprotected|safe|neighborhood> <random|noguess/<attempts>>
+// status <playing|won|lost>
+// <time> <o|c|f> <x> <y>
+// ...
+// ```
+//
+// The `topology` line is omitted for classic grids, the `marks` line for games
+// without question marks and the `lives` line for classic rules. The `undo`
+// line marks games where actions were undone, which only keep the actions
+// that were not undone. Instead of the `seed` line, boards with a fixed layout
+// list their mines as `mines <x>,<y> <x>,<y> ...`. Custom topologies are
+// written as `topology custom <name> <wrap|nowrap> <dx>,<dy> ...`. Times are
+// milliseconds since the first action.
+
+const HEADER: &str = "msreplay";
+const VERSION: u32 = 1;
+
+// Replays usually come from untrusted sources, so boards are limited to a
+// size that can be verified quickly
+pub const MAX_FIELDS: usize = 1 << 20;
+
+// Every no-guess attempt generates and solves a whole layout, so replays are
+// limited to this many attempts for the same reason
+pub const MAX_ATTEMPTS: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayBoard {
+  Seeded {
+    seed: u64,
+    first_click: FirstClick,
+    generation: Generation,
+  },
+  Mines(Vec<Position>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replay {
+  pub topology: String,
+  pub flag_cycle: FlagCycle,
+  pub lives: Option<u32>,
+  pub undo_used: bool,
+  pub width: usize,
+  pub height: usize,
+  pub mine_count: usize,
+  pub board: ReplayBoard,
+  pub status: GameStatus,
+  pub actions: Vec<(u64, Action)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+  Parse {
+    line: usize,
+    message: String,
+  },
+  UnknownTopology(String),
+  InvalidBoard(MinesweeperError),
+  BoardTooLarge {
+    width: usize,
+    height: usize,
+  },
+  MineCountMismatch {
+    expected: usize,
+    actual: usize,
+  },
+  // The player undid actions, so the replay does not show everything that
+  // happened
+  UndoUsed,
+  InvalidAction {
+    index: usize,
+  },
+  TimeOutOfOrder {
+    index: usize,
+  },
+  StatusMismatch {
+    expected: GameStatus,
+    actual: GameStatus,
+  },
+}
+
+impl Display for ReplayError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ReplayError::Parse { line, message } => {
+        write!(f, "parse error on line {}: {}", line, message)
+      }
+      ReplayError::UnknownTopology(name) => {
+        write!(f, "unknown topology '{}'", name)
+      }
+      ReplayError::InvalidBoard(err) => write!(f, "invalid board: {}", err),
+      ReplayError::BoardTooLarge { width, height } => write!(
+        f,
+        "board {}x{} has more than {} fields",
+        width, height, MAX_FIELDS
+      ),
+      ReplayError::MineCountMismatch { expected, actual } => write!(
+        f,
+        "replay should have {} mines but lists {}",
+        expected, actual
+      ),
+      ReplayError::UndoUsed => {
+        write!(f, "actions were undone during the game")
+      }
+      ReplayError::InvalidAction { index } => {
+        write!(f, "action {} cannot be performed", index)
+      }
+      ReplayError::TimeOutOfOrder { index } => {
+        write!(f, "action {} happens before the previous action", index)
+      }
+      ReplayError::StatusMismatch { expected, actual } => write!(
+        f,
+        "replay should end with status {:?} but ends with {:?}",
+        expected, actual
+      ),
+    }
+  }
+}
+
+impl Error for ReplayError {}
+
+impl Replay {
+  pub fn from_game<T: Topology>(ms: &Minesweeper<T>) -> Replay {
+    Replay {
+      topology: ms.topology().spec(),
+      flag_cycle: ms.flag_cycle(),
+      lives: ms.lives(),
+      undo_used: ms.undo_used(),
+      width: ms.width(),
+      height: ms.height(),
+      mine_count: ms.mine_count(),
+      board: match ms.seed() {
+        Some(seed) => ReplayBoard::Seeded {
+          seed,
+          first_click: ms.first_click(),
+          generation: ms.generation(),
+        },
+        None => {
+          let mut mines = ms.iter_mines().collect::<Vec<_>>();
+          mines.sort_by_key(|&(x, y)| (y, x));
+
+          ReplayBoard::Mines(mines)
+        }
+      },
+      status: ms.status(),
+      actions: ms
+        .history()
+        .iter()
+        .map(|record| (record.time, record.action))
+        .collect(),
+    }
+  }
+
+  pub fn new_game(&self) -> Result<Minesweeper<AnyTopology>, ReplayError> {
+    let topology = AnyTopology::from_spec(&self.topology)
+      .ok_or_else(|| ReplayError::UnknownTopology(self.topology.clone()))?;
+
+    if self
+      .width
+      .checked_mul(self.height)
+      .is_some_and(|field_count| field_count > MAX_FIELDS)
+    {
+      return Err(ReplayError::BoardTooLarge {
+        width: self.width,
+        height: self.height,
+      });
+    }
+
+    let mut ms = match &self.board {
+      ReplayBoard::Seeded {
+        seed,
+        first_click,
+        generation,
+      } => Minesweeper::try_with_topology(
+        topology,
+        self.width,
+        self.height,
+        self.mine_count,
+        GameOptions {
+          seed: Some(*seed),
+          first_click: *first_click,
+          generation: *generation,
+          flag_cycle: self.flag_cycle,
+          lives: self.lives,
+        },
+      )
+      .map_err(ReplayError::InvalidBoard)?,
+      ReplayBoard::Mines(mines) => Minesweeper::try_with_topology_and_mines(
+        topology,
+        self.width,
+        self.height,
+        mines.iter().copied(),
+      )
+      .map_err(ReplayError::InvalidBoard)?,
+    };
+
+    // Duplicate or out of bounds mines also end up here
+    if ms.mine_count() != self.mine_count {
+      return Err(ReplayError::MineCountMismatch {
+        expected: self.mine_count,
+        actual: ms.mine_count(),
+      });
+    }
+
+    ms.set_flag_cycle(self.flag_cycle);
+    ms.set_lives(self.lives);
+
+    Ok(ms)
+  }
+
+  // Re-executes all actions on a fresh board, failing if an action has no
+  // effect or does not match the board state
+  pub fn play(&self) -> Result<Minesweeper<AnyTopology>, ReplayError> {
+    let mut ms = self.new_game()?;
+
+    play_actions(&mut ms, &self.actions)?;
+
+    Ok(ms)
+  }
+
+  // Checks that the replay reproduces its result without undoing anything,
+  // as needed for high scores
+  pub fn verify(&self) -> Result<Minesweeper<AnyTopology>, ReplayError> {
+    if self.undo_used {
+      return Err(ReplayError::UndoUsed);
+    }
+
+    let ms = self.play()?;
+
+    if ms.status() != self.status {
+      return Err(ReplayError::StatusMismatch {
+        expected: self.status,
+        actual: ms.status(),
+      });
+    }
+
+    Ok(ms)
+  }
+
+  pub fn duration(&self) -> u64 {
+    self.actions.last().map(|&(time, _)| time).unwrap_or(0)
+  }
+}
+
+// Executes the actions of a replay or saved game in order
+pub(crate) fn play_actions<T: Topology>(
+  ms: &mut Minesweeper<T>,
+  actions: &[(u64, Action)],
+) -> Result<(), ReplayError> {
+  let mut last_time = 0;
+
+  for (index, &(time, action)) in actions.iter().enumerate() {
+    if time < last_time {
+      return Err(ReplayError::TimeOutOfOrder { index });
+    }
+
+    let history_len = ms.history().len();
+    let (Action::Open((x, y))
+    | Action::Chord((x, y))
+    | Action::ToggleFlag((x, y))) = action;
+    let valid = x < ms.width()
+      && y < ms.height()
+      && match action {
+        Action::Open(pos) => !ms.is_open(pos),
+        Action::Chord(pos) => ms.is_open(pos),
+        Action::ToggleFlag(_) => true,
+      };
+
+    if !valid {
+      return Err(ReplayError::InvalidAction { index });
+    }
+
+    match action {
+      Action::Open(pos) | Action::Chord(pos) => ms.open_at(pos, time),
+      Action::ToggleFlag(pos) => ms.toggle_flag_at(pos, time),
+    };
+
+    if ms.history().len() == history_len {
+      return Err(ReplayError::InvalidAction { index });
+    }
+
+    last_time = time;
+  }
+
+  Ok(())
+}
+
+impl Display for Replay {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(
+      f,
+      "{} {} {} {} {}",
+      HEADER, VERSION, self.width, self.height, self.mine_count
+    )?;
+
+    if self.topology != "grid" {
+      writeln!(f, "topology {}", self.topology)?;
+    }
+
+    if self.flag_cycle == FlagCycle::FlagAndQuestion {
+      writeln!(f, "marks question")?;
+    }
+
+    if let Some(lives) = self.lives {
+      writeln!(f, "lives {}", lives)?;
+    }
+
+    if self.undo_used {
+      writeln!(f, "undo used")?;
+    }
+
+    match &self.board {
+      ReplayBoard::Seeded {
+        seed,
+        first_click,
+        generation,
+      } => writeln!(
+        f,
+        "seed {} {}",
+        seed,
+        format_board_options(*first_click, *generation)
+      )?,
+      ReplayBoard::Mines(mines) => {
+        f.write_str("mines")?;
+
+        for (x, y) in mines {
+          write!(f, " {},{}", x, y)?;
+        }
+
+        writeln!(f)?;
+      }
+    }
+
+    writeln!(f, "status {}", self.status.name())?;
+
+    for &(time, action) in &self.actions {
+      write_action(f, time, action)?;
+    }
+
+    Ok(())
+  }
+}
+
+// Writes one action as a `<time> <o|c|f> <x> <y>` line
+pub(crate) fn write_action(
+  f: &mut dyn fmt::Write,
+  time: u64,
+  action: Action,
+) -> fmt::Result {
+  let (kind, (x, y)) = match action {
+    Action::Open(pos) => ('o', pos),
+    Action::Chord(pos) => ('c', pos),
+    Action::ToggleFlag(pos) => ('f', pos),
+  };
+
+  writeln!(f, "{} {} {} {}", time, kind, x, y)
+}
+
+pub(crate) fn parse_action(
+  line: usize,
+  tokens: &[&str],
+) -> Result<(u64, Action), ReplayError> {
+  let time = parse_number(line, tokens.first().copied())?;
+  let pos = (
+    parse_number(line, tokens.get(2).copied())?,
+    parse_number(line, tokens.get(3).copied())?,
+  );
+  let action = match tokens.get(1) {
+    Some(&"o") => Action::Open(pos),
+    Some(&"c") => Action::Chord(pos),
+    Some(&"f") => Action::ToggleFlag(pos),
+    _ => return Err(parse_error(line, "invalid action")),
+  };
+
+  Ok((time, action))
+}
+
+// Formats how mines are placed as `<first click> <generation>`, as used on the
+// `seed` line
+pub(crate) fn format_board_options(
+  first_click: FirstClick,
+  generation: Generation,
+) -> String {
+  let first_click = match first_click {
+    FirstClick::Unprotected => "unprotected",
+    FirstClick::SafeField => "safe",
+    FirstClick::SafeNeighborhood => "neighborhood",
+  };
+
+  match generation {
+    Generation::Random => format!("{} random", first_click),
+    Generation::NoGuess { max_attempts } => {
+      format!("{} noguess/{}", first_click, max_attempts)
+    }
+  }
+}
+
+pub(crate) fn parse_board_options(
+  tokens: &[&str],
+) -> Result<(FirstClick, Generation), &'static str> {
+  let first_click = match tokens.first() {
+    Some(&"unprotected") => FirstClick::Unprotected,
+    Some(&"safe") => FirstClick::SafeField,
+    Some(&"neighborhood") => FirstClick::SafeNeighborhood,
+    _ => return Err("invalid first click mode"),
+  };
+  let generation = match tokens.get(1).map(|token| token.split_once('/')) {
+    Some(None) if tokens[1] == "random" => Generation::Random,
+    Some(Some(("noguess", max_attempts))) => Generation::NoGuess {
+      max_attempts: max_attempts
+        .parse()
+        .map_err(|_| "invalid generation mode")?,
+    },
+    _ => return Err("invalid generation mode"),
+  };
+
+  Ok((first_click, generation))
+}
+
+// Also used by saved games, whose errors convert from `ReplayError`
+pub(crate) fn parse_error(
+  line: usize,
+  message: impl Into<String>,
+) -> ReplayError {
+  ReplayError::Parse {
+    line,
+    message: message.into(),
+  }
+}
+
+pub(crate) fn parse_number<T: FromStr>(
+  line: usize,
+  token: Option<&str>,
+) -> Result<T, ReplayError> {
+  let token =
+    token.ok_or_else(|| parse_error(line, "unexpected end of line"))?;
+
+  token
+    .parse()
+    .map_err(|_| parse_error(line, format!("invalid number '{}'", token)))
+}
+
+fn parse_position(line: usize, token: &str) -> Result<Position, ReplayError> {
+  let (x, y) = token.split_once(',').ok_or_else(|| {
+    parse_error(line, format!("invalid position '{}'", token))
+  })?;
+
+  Ok((parse_number(line, Some(x))?, parse_number(line, Some(y))?))
+}
+
+impl FromStr for Replay {
+  type Err = ReplayError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut lines = s
+      .lines()
+      .enumerate()
+      .map(|(i, line)| (i + 1, line.split_whitespace().collect::<Vec<_>>()))
+      .filter(|(_, tokens)| !tokens.is_empty());
+
+    let (line, header) =
+      lines.next().ok_or_else(|| parse_error(1, "empty replay"))?;
+
+    if header.first() != Some(&HEADER) {
+      return Err(parse_error(line, "missing replay header"));
+    }
+
+    if parse_number::<u32>(line, header.get(1).copied())? != VERSION {
+      return Err(parse_error(line, "unsupported replay version"));
+    }
+
+    let width = parse_number(line, header.get(2).copied())?;
+    let height = parse_number(line, header.get(3).copied())?;
+    let mine_count = parse_number(line, header.get(4).copied())?;
+
+    let (mut line, mut board) = lines
+      .next()
+      .ok_or_else(|| parse_error(line, "missing board"))?;
+    let mut topology = "grid".to_string();
+    let mut flag_cycle = FlagCycle::Flag;
+    let mut lives = None;
+    let mut undo_used = false;
+
+    loop {
+      match board.first() {
+        Some(&"topology") => {
+          if board.len() < 2 {
+            return Err(parse_error(line, "missing topology name"));
+          }
+
+          topology = board[1..].join(" ");
+        }
+        Some(&"marks") if board.get(1) == Some(&"question") => {
+          flag_cycle = FlagCycle::FlagAndQuestion;
+        }
+        Some(&"marks") => return Err(parse_error(line, "invalid marks")),
+        Some(&"lives") => {
+          lives = Some(parse_number(line, board.get(1).copied())?);
+        }
+        Some(&"undo") if board.get(1) == Some(&"used") => undo_used = true,
+        Some(&"undo") => return Err(parse_error(line, "invalid undo line")),
+        _ => break,
+      }
+
+      (line, board) = lines
+        .next()
+        .ok_or_else(|| parse_error(line, "missing board"))?;
+    }
+
+    let board = match board.first() {
+      Some(&"seed") => {
+        let (first_click, generation) =
+          parse_board_options(board.get(2..).unwrap_or(&[]))
+            .map_err(|message| parse_error(line, message))?;
+
+        if let Generation::NoGuess { max_attempts } = generation {
+          if max_attempts > MAX_ATTEMPTS {
+            return Err(parse_error(
+              line,
+              format!("more than {} no-guess attempts", MAX_ATTEMPTS),
+            ));
+          }
+        }
+
+        ReplayBoard::Seeded {
+          seed: parse_number(line, board.get(1).copied())?,
+          first_click,
+          generation,
+        }
+      }
+      Some(&"mines") => ReplayBoard::Mines(
+        board[1..]
+          .iter()
+          .map(|token| parse_position(line, token))
+          .collect::<Result<_, _>>()?,
+      ),
+      _ => return Err(parse_error(line, "expected seed or mines")),
+    };
+
+    let (line, status) = lines
+      .next()
+      .ok_or_else(|| parse_error(line, "missing status"))?;
+    let status = match status[..] {
+      ["status", name] => GameStatus::from_name(name),
+      _ => None,
+    }
+    .ok_or_else(|| parse_error(line, "invalid status"))?;
+
+    let actions = lines
+      .map(|(line, tokens)| parse_action(line, &tokens))
+      .collect::<Result<_, _>>()?;
+
+    Ok(Replay {
+      topology,
+      flag_cycle,
+      lives,
+      undo_used,
+      width,
+      height,
+      mine_count,
+      board,
+      status,
+      actions,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Replay, ReplayError};
+  use crate::{
+    solver,
+    topology::{AnyTopology, Custom, Topology, Torus},
+    FlagCycle, GameStatus, Minesweeper, MinesweeperError,
+  };
+
+  #[test]
+  fn round_trip_seeded_game() {
+    let mut ms = Minesweeper::with_seed(9, 9, 10, 7);
+    ms.open_at((4, 4), 0);
+    solver::solve_to_end(&mut ms);
+
+    let replay = Replay::from_game(&ms);
+    let parsed = replay.to_string().parse::<Replay>().unwrap();
+
+    assert_eq!(parsed, replay);
+    assert_eq!(parsed.verify().unwrap().status(), ms.status());
+  }
+
+  #[test]
+  fn round_trip_question_marks() {
+    let mut ms = Minesweeper::with_mines(3, 2, [(1, 0)]);
+    ms.set_flag_cycle(FlagCycle::FlagAndQuestion);
+    ms.open_at((0, 0), 0);
+    ms.toggle_flag_at((1, 0), 100);
+    ms.toggle_flag_at((1, 0), 200);
+
+    let text = Replay::from_game(&ms).to_string();
+
+    assert_eq!(text.lines().nth(1), Some("marks question"));
+
+    let replayed = text.parse::<Replay>().unwrap().verify().unwrap();
+
+    assert!(replayed.is_marked((1, 0)));
+  }
+
+  #[test]
+  fn round_trip_lives() {
+    let mut ms = Minesweeper::with_mines(4, 1, [(0, 0), (3, 0)]);
+    ms.set_lives(Some(3));
+    ms.open_at((0, 0), 0);
+    ms.open_at((1, 0), 100);
+
+    let text = Replay::from_game(&ms).to_string();
+    let replayed = text.parse::<Replay>().unwrap().verify().unwrap();
+
+    assert_eq!(replayed.lives_left(), Some(2));
+    assert_eq!(replayed.status(), GameStatus::Playing);
+  }
+
+  #[test]
+  fn verify_mine_layout() {
+    let text = "msreplay 1 4 2 2\n\
+      mines 2,0 3,1\n\
+      status lost\n\
+      0 f 3 1\n\
+      1200 o 0 0\n\
+      2500 o 2 0\n";
+    let replay = text.parse::<Replay>().unwrap();
+
+    assert_eq!(replay.duration(), 2500);
+    assert!(replay.verify().is_ok());
+  }
+
+  #[test]
+  fn round_trip_topology() {
+    let mut ms =
+      Minesweeper::with_topology_and_mines(Torus, 4, 4, [(0, 0), (3, 3)]);
+    ms.open_at((1, 2), 0);
+
+    let text = Replay::from_game(&ms).to_string();
+
+    assert_eq!(text.lines().nth(1), Some("topology torus"));
+
+    let replay = text.parse::<Replay>().unwrap();
+
+    assert_eq!(replay.verify().unwrap().topology().name(), "torus");
+
+    let replay = text.replace("torus", "moebius").parse::<Replay>().unwrap();
+
+    assert_eq!(
+      replay.verify().unwrap_err(),
+      ReplayError::UnknownTopology("moebius".to_string())
+    );
+  }
+
+  #[test]
+  fn round_trip_custom_topology() {
+    let cross = Custom {
+      name: "cross".to_string(),
+      offsets: vec![(0, -1), (1, 0), (0, 1), (-1, 0)],
+      wraps: false,
+    };
+    let mut ms = Minesweeper::with_topology_and_mines(
+      AnyTopology::Custom(cross),
+      4,
+      4,
+      [(0, 0), (3, 3)],
+    );
+    ms.open_at((2, 1), 0);
+
+    let text = Replay::from_game(&ms).to_string();
+
+    assert_eq!(
+      text.lines().nth(1),
+      Some("topology custom cross nowrap 0,-1 1,0 0,1 -1,0")
+    );
+    assert_eq!(
+      text.parse::<Replay>().unwrap().verify().unwrap().snapshot(),
+      ms.snapshot()
+    );
+  }
+
+  #[test]
+  fn reject_tampered_replay() {
+    let text = "msreplay 1 4 2 2\n\
+      mines 2,0 3,1\n\
+      status won\n\
+      0 o 0 0\n\
+      100 o 2 0\n";
+    let replay = text.parse::<Replay>().unwrap();
+
+    assert_eq!(
+      replay.verify().unwrap_err(),
+      ReplayError::StatusMismatch {
+        expected: GameStatus::Won,
+        actual: GameStatus::Lost,
+      }
+    );
+
+    let text = "msreplay 1 4 2 2\nmines 2,0 3,1\nstatus won\n0 c 0 0\n";
+    let replay = text.parse::<Replay>().unwrap();
+
+    assert_eq!(
+      replay.verify().unwrap_err(),
+      ReplayError::InvalidAction { index: 0 }
+    );
+  }
+
+  #[test]
+  fn reject_undone_games() {
+    // Opening the mine, undoing it and then playing around it
+    let mut ms = Minesweeper::with_mines(4, 2, [(2, 0), (3, 1)]);
+    ms.open_at((2, 0), 0);
+    ms.undo();
+    ms.open_at((0, 0), 100);
+    ms.open_at((3, 0), 200);
+    ms.open_at((2, 1), 300);
+
+    let text = Replay::from_game(&ms).to_string();
+    let replay = text.parse::<Replay>().unwrap();
+
+    assert_eq!(ms.status(), GameStatus::Won);
+    assert!(text.contains("\nundo used\n"));
+    assert_eq!(replay.verify().unwrap_err(), ReplayError::UndoUsed);
+    assert_eq!(replay.play().unwrap().status(), GameStatus::Won);
+  }
+
+  #[test]
+  fn reject_invalid_boards() {
+    let verify = |text: &str| text.parse::<Replay>().unwrap().verify();
+
+    assert!(matches!(
+      verify("msreplay 1 4294967296 4294967297 0\nmines\nstatus won\n"),
+      Err(ReplayError::InvalidBoard(
+        MinesweeperError::InvalidDimensions { .. }
+      ))
+    ));
+    assert!(matches!(
+      verify("msreplay 1 0 5 0\nmines\nstatus won\n"),
+      Err(ReplayError::InvalidBoard(
+        MinesweeperError::InvalidDimensions { .. }
+      ))
+    ));
+    assert!(matches!(
+      verify("msreplay 1 100000 100000 10\nmines 0,0\nstatus won\n"),
+      Err(ReplayError::BoardTooLarge { .. })
+    ));
+    assert!(matches!(
+      verify("msreplay 1 100000 100000 10\nseed 1 safe random\nstatus won\n"),
+      Err(ReplayError::BoardTooLarge { .. })
+    ));
+    assert_eq!(
+      verify("msreplay 1 4 2 7\nmines 2,0 3,1\nstatus won\n").unwrap_err(),
+      ReplayError::MineCountMismatch {
+        expected: 7,
+        actual: 2
+      }
+    );
+    assert!(matches!(
+      verify("msreplay 1 2 1 1\nmines 0,0 1,0\nstatus won\n"),
+      Err(ReplayError::InvalidBoard(
+        MinesweeperError::TooManyMines { .. }
+      ))
+    ));
+    assert!(matches!(
+      "msreplay 1 8 8 10\nseed 1 safe noguess/100000000\nstatus won\n"
+        .parse::<Replay>(),
+      Err(ReplayError::Parse { line: 2, .. })
+    ));
+    assert!("msreplay 1 8 8 10\nseed 1 safe noguess/10000\nstatus won\n"
+      .parse::<Replay>()
+      .is_ok());
+  }
+}
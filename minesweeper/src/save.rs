@@ -0,0 +1,638 @@
+use crate::{
+  minesweeper::{
+    Action, FlagCycle, GameOptions, GameStatus, Minesweeper, MinesweeperError,
+    Position,
+  },
+  replay::{
+    format_board_options, parse_action, parse_board_options, parse_error,
+    parse_number, play_actions, write_action, ReplayError,
+  },
+  stats::Stats,
+  topology::{AnyTopology, Topology},
+};
+use std::{
+  collections::HashSet,
+  error::Error,
+  fmt::{self, Display, Write},
+  str::FromStr,
+};
+
+// Saved games are stored as plain text:
+//
+// ```
+// mssave 1 <width> <height> <mine count>
+// topology <name>
+// marks question
+// lives <lives>
+// undo used
+// seed <seed> <unprotected|safe|neighborhood> <random|noguess/<attempts>>
+// guessfree <yes|no>
+// mines <fields>
+// open <fields>
+// flagged <fields>
+// marked <fields>
+// detonated <fields>
+// status <playing|won|lost>
+// stats <time> <left clicks> <right clicks> <chord clicks>
+// <time> <o|c|f> <x> <y>
+// ...
+// ```
+//
+// Sets of fields are bitmaps in row-major order, packed six fields per
+// character. The `topology`, `marks`, `lives`, `undo` and `seed` lines and
+// the actions are written like in replays, so that loaded games can still be
+// exported as replays. The `guessfree` line is only written once no-guess
+// generation has run and the `mines` line once the mines are placed, empty
+// sets of fields are omitted.
+//
+// Loading replays the actions on the saved mines, or on the seeded board if
+// there are no mines yet, and checks the result against the saved fields.
+// Undone actions are not part of the history and cannot be redone after
+// loading.
+
+const HEADER: &str = "mssave";
+const VERSION: u32 = 1;
+const ALPHABET: &[u8; 64] =
+  b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SaveError {
+  Parse {
+    line: usize,
+    message: String,
+  },
+  UnknownTopology(String),
+  InvalidBoard(MinesweeperError),
+  // The saved actions cannot be replayed on the saved board
+  InvalidHistory(ReplayError),
+  // Replaying the actions does not lead to the saved fields
+  FieldMismatch,
+  StatusMismatch {
+    expected: GameStatus,
+    actual: GameStatus,
+  },
+}
+
+impl Display for SaveError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      SaveError::Parse { line, message } => {
+        write!(f, "parse error on line {}: {}", line, message)
+      }
+      SaveError::UnknownTopology(name) => {
+        write!(f, "unknown topology '{}'", name)
+      }
+      SaveError::InvalidBoard(err) => write!(f, "invalid board: {}", err),
+      SaveError::InvalidHistory(err) => write!(f, "invalid history: {}", err),
+      SaveError::FieldMismatch => {
+        write!(f, "the saved actions do not lead to the saved fields")
+      }
+      SaveError::StatusMismatch { expected, actual } => write!(
+        f,
+        "saved game should have status {:?} but has {:?}",
+        expected, actual
+      ),
+    }
+  }
+}
+
+impl Error for SaveError {}
+
+impl From<ReplayError> for SaveError {
+  fn from(err: ReplayError) -> SaveError {
+    match err {
+      ReplayError::Parse { line, message } => {
+        SaveError::Parse { line, message }
+      }
+      err => SaveError::InvalidHistory(err),
+    }
+  }
+}
+
+impl<T: Topology> Minesweeper<T> {
+  pub fn save(&self) -> String {
+    let mut out = String::new();
+
+    // Writing to a `String` cannot fail
+    self.write_save(&mut out).unwrap();
+    out
+  }
+
+  fn write_save(&self, out: &mut String) -> fmt::Result {
+    writeln!(
+      out,
+      "{} {} {} {} {}",
+      HEADER,
+      VERSION,
+      self.width(),
+      self.height(),
+      self.mine_count()
+    )?;
+
+    if self.topology().spec() != "grid" {
+      writeln!(out, "topology {}", self.topology().spec())?;
+    }
+
+    if self.flag_cycle() == FlagCycle::FlagAndQuestion {
+      writeln!(out, "marks question")?;
+    }
+
+    if let Some(lives) = self.lives() {
+      writeln!(out, "lives {}", lives)?;
+    }
+
+    if self.undo_used() {
+      writeln!(out, "undo used")?;
+    }
+
+    if let Some(seed) = self.seed() {
+      writeln!(
+        out,
+        "seed {} {}",
+        seed,
+        format_board_options(self.first_click(), self.generation())
+      )?;
+    }
+
+    if let Some(guess_free) = self.guess_free() {
+      writeln!(out, "guessfree {}", if guess_free { "yes" } else { "no" })?;
+    }
+
+    if self.mines_placed() {
+      writeln!(out, "mines {}", self.encode_fields(|pos| self.is_mine(pos)))?;
+    }
+
+    let sets: [(&str, &dyn Fn(Position) -> bool); 4] = [
+      ("open", &|pos| self.is_open(pos)),
+      ("flagged", &|pos| self.is_flagged(pos)),
+      ("marked", &|pos| self.is_marked(pos)),
+      ("detonated", &|pos| self.is_detonated(pos)),
+    ];
+
+    for (name, contains) in sets {
+      if self.iter_positions().any(contains) {
+        writeln!(out, "{} {}", name, self.encode_fields(contains))?;
+      }
+    }
+
+    let stats = self.stats();
+
+    writeln!(out, "status {}", self.status().name())?;
+    writeln!(
+      out,
+      "stats {} {} {} {}",
+      stats.time, stats.left_clicks, stats.right_clicks, stats.chord_clicks
+    )?;
+
+    for record in self.history() {
+      write_action(out, record.time, record.action)?;
+    }
+
+    Ok(())
+  }
+
+  fn encode_fields(&self, contains: impl Fn(Position) -> bool) -> String {
+    let positions = self.iter_positions().collect::<Vec<_>>();
+
+    positions
+      .chunks(6)
+      .map(|chunk| {
+        let bits = chunk
+          .iter()
+          .enumerate()
+          .filter(|&(_, &pos)| contains(pos))
+          .fold(0, |bits, (k, _)| bits | 1 << k);
+
+        ALPHABET[bits] as char
+      })
+      .collect()
+  }
+}
+
+impl Minesweeper<AnyTopology> {
+  // Restores a game written by `save`. Games saved before the first click
+  // without a seed get a new random board.
+  pub fn load(s: &str) -> Result<Minesweeper<AnyTopology>, SaveError> {
+    s.parse::<SavedGame>()?.into_game()
+  }
+}
+
+#[derive(Debug, Default)]
+struct SavedGame {
+  width: usize,
+  height: usize,
+  mine_count: usize,
+  topology: String,
+  options: GameOptions,
+  guess_free: Option<bool>,
+  undo_used: bool,
+  mines: Option<HashSet<Position>>,
+  open: HashSet<Position>,
+  flagged: HashSet<Position>,
+  marked: HashSet<Position>,
+  detonated: HashSet<Position>,
+  status: Option<GameStatus>,
+  stats: Stats,
+  actions: Vec<(u64, Action)>,
+}
+
+impl SavedGame {
+  fn into_game(self) -> Result<Minesweeper<AnyTopology>, SaveError> {
+    let topology = AnyTopology::from_spec(&self.topology)
+      .ok_or_else(|| SaveError::UnknownTopology(self.topology.clone()))?;
+
+    let mut ms = match &self.mines {
+      Some(mines) => Minesweeper::try_with_topology_and_mines(
+        topology,
+        self.width,
+        self.height,
+        mines.iter().copied(),
+      ),
+      None => Minesweeper::try_with_topology(
+        topology,
+        self.width,
+        self.height,
+        self.mine_count,
+        self.options,
+      ),
+    }
+    .map_err(SaveError::InvalidBoard)?;
+
+    ms.set_flag_cycle(self.options.flag_cycle);
+    ms.set_lives(self.options.lives);
+    play_actions(&mut ms, &self.actions)?;
+
+    let matches = |saved: &HashSet<Position>,
+                   contains: &dyn Fn(Position) -> bool| {
+      ms.iter_positions()
+        .all(|pos| saved.contains(&pos) == contains(pos))
+    };
+
+    if !matches(&self.open, &|pos| ms.is_open(pos))
+      || !matches(&self.flagged, &|pos| ms.is_flagged(pos))
+      || !matches(&self.marked, &|pos| ms.is_marked(pos))
+      || !matches(&self.detonated, &|pos| ms.is_detonated(pos))
+    {
+      return Err(SaveError::FieldMismatch);
+    }
+
+    ms.restore(self.options, self.guess_free, self.undo_used, self.stats);
+
+    match self.status {
+      Some(expected) if expected != ms.status() => {
+        Err(SaveError::StatusMismatch {
+          expected,
+          actual: ms.status(),
+        })
+      }
+      _ => Ok(ms),
+    }
+  }
+}
+
+fn decode_fields(
+  line: usize,
+  token: Option<&str>,
+  width: usize,
+  height: usize,
+) -> Result<HashSet<Position>, SaveError> {
+  let token =
+    token.ok_or_else(|| parse_error(line, "unexpected end of line"))?;
+
+  if token.len() != (width * height).div_ceil(6) {
+    return Err(parse_error(line, "field set has the wrong length").into());
+  }
+
+  let mut fields = HashSet::new();
+
+  for (i, c) in token.bytes().enumerate() {
+    let bits = ALPHABET
+      .iter()
+      .position(|&a| a == c)
+      .ok_or_else(|| parse_error(line, "invalid character in field set"))?;
+
+    for k in 0..6 {
+      let index = i * 6 + k;
+
+      if bits & 1 << k != 0 {
+        if index >= width * height {
+          return Err(
+            parse_error(line, "field set has the wrong length").into(),
+          );
+        }
+
+        fields.insert((index % width, index / width));
+      }
+    }
+  }
+
+  Ok(fields)
+}
+
+impl FromStr for SavedGame {
+  type Err = SaveError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut lines = s
+      .lines()
+      .enumerate()
+      .map(|(i, line)| (i + 1, line.split_whitespace().collect::<Vec<_>>()))
+      .filter(|(_, tokens)| !tokens.is_empty());
+
+    let (line, header) = lines
+      .next()
+      .ok_or_else(|| parse_error(1, "empty saved game"))?;
+
+    if header.first() != Some(&HEADER) {
+      return Err(parse_error(line, "missing saved game header").into());
+    }
+
+    if parse_number::<u32>(line, header.get(1).copied())? != VERSION {
+      return Err(parse_error(line, "unsupported saved game version").into());
+    }
+
+    let width = parse_number(line, header.get(2).copied())?;
+    let height = parse_number(line, header.get(3).copied())?;
+    let mut game = SavedGame {
+      width,
+      height,
+      mine_count: parse_number(line, header.get(4).copied())?,
+      topology: "grid".to_string(),
+      ..SavedGame::default()
+    };
+
+    if width.checked_mul(height).is_none_or(|count| count == 0) {
+      return Err(SaveError::InvalidBoard(
+        MinesweeperError::InvalidDimensions { width, height },
+      ));
+    }
+
+    for (line, tokens) in lines {
+      let fields = |token| decode_fields(line, token, width, height);
+
+      match tokens[0] {
+        "topology" if tokens.len() > 1 => game.topology = tokens[1..].join(" "),
+        "topology" => {
+          return Err(parse_error(line, "missing topology name").into())
+        }
+        "marks" => match tokens.get(1) {
+          Some(&"question") => {
+            game.options.flag_cycle = FlagCycle::FlagAndQuestion
+          }
+          _ => return Err(parse_error(line, "invalid mark mode").into()),
+        },
+        "lives" => {
+          game.options.lives = Some(parse_number(line, tokens.get(1).copied())?)
+        }
+        "undo" => match tokens.get(1) {
+          Some(&"used") => game.undo_used = true,
+          _ => return Err(parse_error(line, "invalid undo line").into()),
+        },
+        "guessfree" => {
+          game.guess_free = Some(match tokens.get(1) {
+            Some(&"yes") => true,
+            Some(&"no") => false,
+            _ => {
+              return Err(parse_error(line, "invalid guessfree line").into())
+            }
+          })
+        }
+        "seed" => {
+          let (first_click, generation) =
+            parse_board_options(tokens.get(2..).unwrap_or(&[]))
+              .map_err(|message| parse_error(line, message))?;
+
+          game.options.seed = Some(parse_number(line, tokens.get(1).copied())?);
+          game.options.first_click = first_click;
+          game.options.generation = generation;
+        }
+        "mines" => {
+          let mines = fields(tokens.get(1).copied())?;
+
+          if mines.len() != game.mine_count {
+            return Err(parse_error(line, "wrong number of mines").into());
+          }
+
+          game.mines = Some(mines);
+        }
+        "open" => game.open = fields(tokens.get(1).copied())?,
+        "flagged" => game.flagged = fields(tokens.get(1).copied())?,
+        "marked" => game.marked = fields(tokens.get(1).copied())?,
+        "detonated" => game.detonated = fields(tokens.get(1).copied())?,
+        "status" => {
+          game.status = Some(
+            tokens
+              .get(1)
+              .and_then(|name| GameStatus::from_name(name))
+              .ok_or_else(|| parse_error(line, "invalid status"))?,
+          )
+        }
+        "stats" => {
+          game.stats = Stats {
+            time: parse_number(line, tokens.get(1).copied())?,
+            left_clicks: parse_number(line, tokens.get(2).copied())?,
+            right_clicks: parse_number(line, tokens.get(3).copied())?,
+            chord_clicks: parse_number(line, tokens.get(4).copied())?,
+            ..Stats::default()
+          }
+        }
+        key if key.starts_with(|c: char| c.is_ascii_digit()) => {
+          game.actions.push(parse_action(line, &tokens)?)
+        }
+        key => {
+          return Err(
+            parse_error(line, format!("unknown line '{}'", key)).into(),
+          )
+        }
+      }
+    }
+
+    if game.mines.is_none() && !game.open.is_empty() {
+      return Err(parse_error(line, "open fields without mines").into());
+    }
+
+    Ok(game)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::SaveError;
+  use crate::{
+    minesweeper::{
+      FirstClick, FlagCycle, GameOptions, GameStatus, Generation, Minesweeper,
+    },
+    replay::{Replay, ReplayError},
+    topology::{AnyTopology, Custom, Grid, Hex, Topology},
+  };
+
+  #[test]
+  fn round_trip_in_progress() {
+    let mut ms = Minesweeper::with_topology(
+      AnyTopology::Hex(Hex),
+      12,
+      7,
+      15,
+      GameOptions {
+        seed: Some(3),
+        first_click: FirstClick::SafeField,
+        flag_cycle: FlagCycle::FlagAndQuestion,
+        lives: Some(3),
+        ..GameOptions::default()
+      },
+    );
+    ms.open_at((6, 3), 0);
+
+    let covered = ms
+      .iter_positions()
+      .filter(|&pos| !ms.is_open(pos))
+      .collect::<Vec<_>>();
+    let mine = covered
+      .iter()
+      .copied()
+      .find(|&pos| ms.is_mine(pos))
+      .unwrap();
+    let safe = covered
+      .iter()
+      .copied()
+      .find(|&pos| !ms.is_mine(pos))
+      .unwrap();
+
+    ms.open_at(mine, 100);
+    ms.toggle_flag_at(safe, 200);
+    ms.toggle_flag_at(safe, 300);
+
+    let loaded = Minesweeper::load(&ms.save()).unwrap();
+
+    assert_eq!(loaded.snapshot(), ms.snapshot());
+    assert_eq!(loaded.lives_left(), Some(2));
+    assert!(loaded.is_detonated(mine));
+    assert!(loaded.is_marked(safe));
+    assert_eq!(loaded.stats().left_clicks, 2);
+    assert_eq!(loaded.stats().right_clicks, 2);
+  }
+
+  #[test]
+  fn round_trip_custom_topology() {
+    let knightish = Custom {
+      name: "jumps".to_string(),
+      offsets: vec![(2, 1), (-2, -1), (3, 0), (-3, 0)],
+      wraps: true,
+    };
+    let mut ms = Minesweeper::with_topology(
+      AnyTopology::Custom(knightish),
+      8,
+      8,
+      10,
+      GameOptions {
+        seed: Some(5),
+        ..GameOptions::default()
+      },
+    );
+    ms.open_at((4, 4), 0);
+
+    let loaded = Minesweeper::load(&ms.save()).unwrap();
+
+    assert_eq!(loaded.topology().spec(), ms.topology().spec());
+    assert_eq!(loaded.snapshot(), ms.snapshot());
+  }
+
+  #[test]
+  fn loaded_games_keep_their_replay() {
+    let mut ms = Minesweeper::with_options(
+      9,
+      9,
+      10,
+      GameOptions {
+        seed: Some(8),
+        first_click: FirstClick::SafeNeighborhood,
+        generation: Generation::NoGuess { max_attempts: 100 },
+        ..GameOptions::default()
+      },
+    );
+    ms.toggle_flag_at((0, 0), 0);
+    ms.open_at((4, 4), 50);
+
+    let loaded = Minesweeper::load(&ms.save()).unwrap();
+    let replay = Replay::from_game(&loaded);
+
+    assert_eq!(replay, Replay::from_game(&ms));
+    assert_eq!(loaded.guess_free(), ms.guess_free());
+    assert_eq!(replay.verify().unwrap().snapshot(), ms.snapshot());
+  }
+
+  #[test]
+  fn undo_is_saved() {
+    let mut ms = Minesweeper::with_mines(4, 1, [(3, 0)]);
+    ms.toggle_flag_at((0, 0), 0);
+    ms.undo();
+    ms.toggle_flag_at((1, 0), 10);
+
+    let loaded = Minesweeper::load(&ms.save()).unwrap();
+
+    assert!(loaded.undo_used());
+    assert!(loaded.is_flagged((1, 0)));
+    assert_eq!(loaded.history().len(), 1);
+  }
+
+  #[test]
+  fn round_trip_before_first_click() {
+    let mut ms = Minesweeper::with_seed(9, 9, 10, 42);
+    ms.toggle_flag((0, 0));
+
+    let mut loaded = Minesweeper::load(&ms.save()).unwrap();
+
+    assert!(!loaded.mines_placed());
+    assert!(loaded.is_flagged((0, 0)));
+
+    ms.open((4, 4));
+    loaded.open((4, 4));
+
+    assert_eq!(loaded.snapshot(), ms.snapshot());
+  }
+
+  #[test]
+  fn round_trip_finished() {
+    let mut ms = Minesweeper::with_topology_and_mines(Grid, 3, 1, [(2, 0)]);
+    ms.open((0, 0));
+
+    let loaded = Minesweeper::load(&ms.save()).unwrap();
+
+    assert_eq!(loaded.status(), GameStatus::Won);
+    assert!(loaded.is_flagged((2, 0)));
+  }
+
+  #[test]
+  fn invalid_saves() {
+    let ms = Minesweeper::with_mines(3, 1, [(2, 0)]);
+    let saved = ms.save();
+
+    assert!(matches!(
+      Minesweeper::load(&saved.replace("mssave 1", "mssave 2")),
+      Err(SaveError::Parse { line: 1, .. })
+    ));
+    assert!(matches!(
+      Minesweeper::load(&saved.replace("mines E", "mines E!")),
+      Err(SaveError::Parse { line: 2, .. })
+    ));
+    assert!(matches!(
+      Minesweeper::load(&saved.replace("playing", "won")),
+      Err(SaveError::StatusMismatch { .. })
+    ));
+
+    let mut ms = Minesweeper::with_mines(3, 1, [(2, 0)]);
+    ms.toggle_flag_at((0, 0), 0);
+    let saved = ms.save();
+
+    assert_eq!(
+      Minesweeper::load(&saved.replace("0 f 0 0", "0 f 1 0")).unwrap_err(),
+      SaveError::FieldMismatch
+    );
+    assert!(matches!(
+      Minesweeper::load(&saved.replace("0 f 0 0", "0 o 5 0")),
+      Err(SaveError::InvalidHistory(ReplayError::InvalidAction {
+        index: 0
+      }))
+    ));
+  }
+}
@@ -0,0 +1,225 @@
+use crate::{
+  minesweeper::{ActionRecord, GameStatus, Minesweeper, Position},
+  topology::Topology,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldState {
+  Covered,
+  Flagged,
+  Marked,
+  Open(u8),
+  // Only revealed once the game is lost
+  Mine,
+  ExplodedMine,
+  WrongFlag,
+}
+
+impl FieldState {
+  // Compact encoding for the frontend: 0-8 are open fields with the given
+  // number of neighboring mines, the other states follow from 9 onwards
+  pub fn to_code(self) -> u8 {
+    match self {
+      FieldState::Open(mine_count) => mine_count,
+      FieldState::Covered => 9,
+      FieldState::Flagged => 10,
+      FieldState::Mine => 11,
+      FieldState::ExplodedMine => 12,
+      FieldState::WrongFlag => 13,
+      FieldState::Marked => 14,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+  // Name of the board topology, see `Topology::name`
+  pub topology: String,
+  pub width: usize,
+  pub height: usize,
+  pub status: GameStatus,
+  // Row-major list of all fields
+  pub fields: Vec<FieldState>,
+}
+
+impl Snapshot {
+  pub fn get(&self, (x, y): Position) -> FieldState {
+    self.fields[y * self.width + x]
+  }
+}
+
+// Fields whose visible state changed through an action, so frontends can
+// update only those instead of rendering a new snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeSet {
+  pub status: GameStatus,
+  pub fields: Vec<(Position, FieldState)>,
+}
+
+impl ChangeSet {
+  pub fn new(status: GameStatus) -> ChangeSet {
+    ChangeSet {
+      status,
+      fields: vec![],
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.fields.is_empty()
+  }
+}
+
+impl<T: Topology> Minesweeper<T> {
+  pub fn field_state(&self, pos: Position) -> FieldState {
+    let lost = self.is_lost();
+
+    if self.is_open(pos) {
+      if self.is_mine(pos) {
+        FieldState::ExplodedMine
+      } else {
+        FieldState::Open(self.neighboring_mines(pos))
+      }
+    } else if self.is_detonated(pos) {
+      FieldState::ExplodedMine
+    } else if self.is_flagged(pos) {
+      if lost && !self.is_mine(pos) {
+        FieldState::WrongFlag
+      } else {
+        FieldState::Flagged
+      }
+    } else if lost && self.is_mine(pos) {
+      FieldState::Mine
+    } else if self.is_marked(pos) {
+      FieldState::Marked
+    } else {
+      FieldState::Covered
+    }
+  }
+
+  // Changes caused by the given action, which must be the last one applied
+  pub(crate) fn changes(&self, record: &ActionRecord) -> ChangeSet {
+    let mut positions = record
+      .opened
+      .iter()
+      .chain(&record.flagged)
+      .chain(&record.unflagged)
+      // Newly marked fields are always unflagged by the same action
+      .chain(&record.unmarked)
+      .copied()
+      .collect::<Vec<_>>();
+
+    // Losing reveals all other mines and wrong flags
+    if record.status_before != record.status_after
+      && record.status_after == GameStatus::Lost
+    {
+      positions.extend(self.iter_positions().filter(|&pos| {
+        !self.is_open(pos) && self.is_mine(pos) != self.is_flagged(pos)
+      }));
+    }
+
+    ChangeSet {
+      status: self.status(),
+      fields: positions
+        .into_iter()
+        .map(|pos| (pos, self.field_state(pos)))
+        .collect(),
+    }
+  }
+
+  pub fn snapshot(&self) -> Snapshot {
+    Snapshot {
+      topology: self.topology().name().to_string(),
+      width: self.width(),
+      height: self.height(),
+      status: self.status(),
+      fields: self
+        .iter_positions()
+        .map(|pos| self.field_state(pos))
+        .collect(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{ChangeSet, FieldState};
+  use crate::{FlagCycle, GameStatus, Minesweeper};
+
+  #[test]
+  fn lost_snapshot() {
+    let mut ms = Minesweeper::with_mines(4, 1, [(2, 0), (3, 0)]);
+    ms.toggle_flag((0, 0));
+    ms.toggle_flag((3, 0));
+    ms.open((2, 0));
+
+    let snapshot = ms.snapshot();
+
+    assert_eq!(snapshot.status, GameStatus::Lost);
+    assert_eq!(
+      snapshot.fields,
+      [
+        FieldState::WrongFlag,
+        FieldState::Covered,
+        FieldState::ExplodedMine,
+        FieldState::Flagged,
+      ]
+    );
+  }
+
+  #[test]
+  fn playing_snapshot() {
+    let mut ms = Minesweeper::with_mines(4, 2, [(2, 0), (3, 1)]);
+    ms.open((0, 0));
+    ms.toggle_flag((3, 1));
+
+    let snapshot = ms.snapshot();
+
+    assert_eq!(snapshot.status, GameStatus::Playing);
+    assert_eq!(snapshot.get((0, 1)), FieldState::Open(0));
+    assert_eq!(snapshot.get((1, 0)), FieldState::Open(1));
+    assert_eq!(snapshot.get((2, 0)), FieldState::Covered);
+    assert_eq!(snapshot.get((3, 1)), FieldState::Flagged);
+  }
+
+  #[test]
+  fn marked_snapshot() {
+    let mut ms = Minesweeper::with_mines(3, 1, [(2, 0)]);
+    ms.set_flag_cycle(FlagCycle::FlagAndQuestion);
+    ms.toggle_flag((1, 0));
+
+    let changes = ms.toggle_flag((1, 0));
+
+    assert_eq!(changes.fields, [((1, 0), FieldState::Marked)]);
+    assert_eq!(ms.snapshot().get((1, 0)), FieldState::Marked);
+    assert_eq!(ms.to_string(), "🟪 ❓ 🟪 \n");
+  }
+
+  #[test]
+  fn change_sets() {
+    let mut ms = Minesweeper::with_mines(4, 2, [(2, 0), (3, 1)]);
+
+    assert_eq!(
+      ms.toggle_flag((3, 0)).fields,
+      [((3, 0), FieldState::Flagged)]
+    );
+
+    let changes = ms.open((0, 0));
+
+    assert_eq!(changes.status, GameStatus::Playing);
+    assert_eq!(changes.fields.len(), 4);
+    assert!(changes.fields.contains(&((1, 0), FieldState::Open(1))));
+    assert_eq!(ms.open((3, 0)), ChangeSet::new(GameStatus::Playing));
+
+    let mut changes = ms.open((2, 0)).fields;
+    changes.sort_by_key(|&(pos, _)| pos);
+
+    assert_eq!(
+      changes,
+      [
+        ((2, 0), FieldState::ExplodedMine),
+        ((3, 0), FieldState::WrongFlag),
+        ((3, 1), FieldState::Mine),
+      ]
+    );
+  }
+}
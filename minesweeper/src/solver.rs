@@ -0,0 +1,262 @@
+use crate::{
+  minesweeper::{GameStatus, Minesweeper, Position},
+  topology::Topology,
+};
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hint {
+  Safe(Position),
+  Mine(Position),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Solution {
+  pub safe: BTreeSet<Position>,
+  // Does not include fields the player has already flagged
+  pub mines: BTreeSet<Position>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Constraint {
+  fields: BTreeSet<Position>,
+  mines: usize,
+}
+
+impl Solution {
+  pub fn is_empty(&self) -> bool {
+    self.safe.is_empty() && self.mines.is_empty()
+  }
+}
+
+// Derives fields that are provably safe or provably mines, looking only at
+// what the player can see. Flags are trusted to be correct.
+pub fn solve<T: Topology>(ms: &Minesweeper<T>) -> Solution {
+  let mut solution = Solution::default();
+
+  if ms.status() != GameStatus::Playing || !ms.mines_placed() {
+    return solution;
+  }
+
+  loop {
+    let constraints = collect_constraints(ms, &solution);
+    let mut safe = BTreeSet::new();
+    let mut mines = BTreeSet::new();
+
+    for constraint in &constraints {
+      if constraint.mines == 0 {
+        safe.extend(constraint.fields.iter().copied());
+      } else if constraint.mines == constraint.fields.len() {
+        mines.extend(constraint.fields.iter().copied());
+      }
+    }
+
+    if safe.is_empty() && mines.is_empty() {
+      for (a, b) in iter_overlapping(&constraints) {
+        compare_constraints(a, b, &mut safe, &mut mines);
+        compare_constraints(b, a, &mut safe, &mut mines);
+      }
+    }
+
+    if safe.is_empty() && mines.is_empty() {
+      break;
+    }
+
+    solution.safe.extend(safe);
+    solution.mines.extend(mines);
+  }
+
+  solution
+}
+
+// Keeps opening provably safe fields until no more progress can be made.
+// Returns whether all fields without mines have been opened.
+pub fn solve_to_end<T: Topology>(ms: &mut Minesweeper<T>) -> bool {
+  loop {
+    let solution = solve(ms);
+
+    if solution.safe.is_empty() {
+      break;
+    }
+
+    for pos in solution.safe {
+      ms.open(pos);
+    }
+  }
+
+  ms.status() == GameStatus::Won
+}
+
+fn is_known_mine<T: Topology>(
+  ms: &Minesweeper<T>,
+  solution: &Solution,
+  pos: Position,
+) -> bool {
+  ms.is_flagged(pos) || solution.mines.contains(&pos)
+}
+
+fn collect_constraints<T: Topology>(
+  ms: &Minesweeper<T>,
+  solution: &Solution,
+) -> Vec<Constraint> {
+  let is_unknown = |pos: Position| {
+    !ms.is_open(pos)
+      && !solution.safe.contains(&pos)
+      && !is_known_mine(ms, solution, pos)
+  };
+
+  let mut constraints = ms
+    .iter_positions()
+    .filter(|&pos| ms.is_open(pos))
+    .filter_map(|pos| {
+      let known_mines = ms
+        .iter_neighbors(pos)
+        .filter(|&neighbor| is_known_mine(ms, solution, neighbor))
+        .count();
+      let fields = ms
+        .iter_neighbors(pos)
+        .filter(|&neighbor| is_unknown(neighbor))
+        .collect::<BTreeSet<_>>();
+
+      (!fields.is_empty()).then(|| Constraint {
+        fields,
+        mines: (ms.neighboring_mines(pos) as usize).saturating_sub(known_mines),
+      })
+    })
+    .collect::<Vec<_>>();
+
+  // The total mine count constrains all remaining unknown fields
+  let known_mines = ms
+    .iter_positions()
+    .filter(|&pos| is_known_mine(ms, solution, pos))
+    .count();
+  let fields = ms
+    .iter_positions()
+    .filter(|&pos| is_unknown(pos))
+    .collect::<BTreeSet<_>>();
+
+  if !fields.is_empty() {
+    constraints.push(Constraint {
+      fields,
+      mines: ms.mine_count().saturating_sub(known_mines),
+    });
+  }
+
+  constraints.sort_by(|a, b| a.fields.cmp(&b.fields));
+  constraints.dedup();
+  constraints
+}
+
+fn iter_overlapping(
+  constraints: &[Constraint],
+) -> impl Iterator<Item = (&Constraint, &Constraint)> {
+  let mut by_field = HashMap::<Position, Vec<usize>>::new();
+
+  for (i, constraint) in constraints.iter().enumerate() {
+    for &pos in &constraint.fields {
+      by_field.entry(pos).or_default().push(i);
+    }
+  }
+
+  let pairs = by_field
+    .values()
+    .flat_map(|indices| {
+      indices
+        .iter()
+        .enumerate()
+        .flat_map(move |(k, &i)| indices[k + 1..].iter().map(move |&j| (i, j)))
+    })
+    .collect::<BTreeSet<_>>();
+
+  pairs
+    .into_iter()
+    .map(move |(i, j)| (&constraints[i], &constraints[j]))
+}
+
+fn compare_constraints(
+  a: &Constraint,
+  b: &Constraint,
+  safe: &mut BTreeSet<Position>,
+  mines: &mut BTreeSet<Position>,
+) {
+  let only_a = a.fields.difference(&b.fields).copied().collect::<Vec<_>>();
+  let only_b = b.fields.difference(&a.fields).copied().collect::<Vec<_>>();
+
+  if only_b.is_empty() {
+    return;
+  }
+
+  if b.mines >= a.mines && b.mines - a.mines == only_b.len() {
+    // All fields exclusive to b must be mines, so the shared fields already
+    // hold all mines of a
+    mines.extend(only_b);
+    safe.extend(only_a);
+  } else if only_a.is_empty() && a.mines == b.mines {
+    // a is a subset of b with the same amount of mines
+    safe.extend(only_b);
+  }
+}
+
+impl<T: Topology> Minesweeper<T> {
+  pub fn hint(&self) -> Option<Hint> {
+    let solution = solve(self);
+
+    solution
+      .safe
+      .iter()
+      .next()
+      .map(|&pos| Hint::Safe(pos))
+      .or_else(|| solution.mines.iter().next().map(|&pos| Hint::Mine(pos)))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{solve, Hint};
+  use crate::Minesweeper;
+
+  #[test]
+  fn single_field_deduction() {
+    // . 1 ? ? ?
+    let mut ms = Minesweeper::with_mines(5, 1, [(2, 0), (4, 0)]);
+    ms.open((0, 0));
+
+    assert_eq!(ms.hint(), Some(Hint::Mine((2, 0))));
+
+    // The remaining mine is a 50/50 guess
+    ms.toggle_flag((2, 0));
+    assert_eq!(ms.hint(), None);
+  }
+
+  #[test]
+  fn subset_deduction() {
+    // . . . .
+    // 1 1 1 1
+    // ? ? ? ?
+    let mut ms = Minesweeper::with_mines(4, 3, [(0, 2), (3, 2)]);
+    ms.open((0, 0));
+
+    let solution = solve(&ms);
+
+    assert!(solution.safe.contains(&(1, 2)));
+    assert!(solution.mines.contains(&(0, 2)));
+  }
+
+  #[test]
+  fn deductions_are_sound() {
+    for seed in 0..30 {
+      let mut ms = Minesweeper::with_seed(12, 12, 25, seed);
+      ms.open((6, 6));
+
+      let solution = solve(&ms);
+
+      for &pos in &solution.safe {
+        assert!(!ms.is_mine(pos));
+      }
+
+      for &pos in &solution.mines {
+        assert!(ms.is_mine(pos));
+      }
+    }
+  }
+}
@@ -0,0 +1,133 @@
+use crate::{
+  minesweeper::{GameStatus, Minesweeper},
+  time,
+  topology::Topology,
+};
+
+// Standard metrics for comparing runs. Efficiency and 3BV/s are only
+// meaningful once the game is won.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Stats {
+  // Minimum number of clicks needed to clear the board, 0 until mines are
+  // placed
+  pub three_bv: usize,
+  // Clicks that open a covered field
+  pub left_clicks: usize,
+  // Clicks that toggle a flag
+  pub right_clicks: usize,
+  // Clicks on open fields
+  pub chord_clicks: usize,
+  // Milliseconds from the first action until the game ended, or until now
+  // while it is still being played
+  pub time: u64,
+}
+
+impl Stats {
+  pub fn clicks(&self) -> usize {
+    self.left_clicks + self.right_clicks + self.chord_clicks
+  }
+
+  pub fn efficiency(&self) -> Option<f64> {
+    (self.clicks() > 0).then(|| self.three_bv as f64 / self.clicks() as f64)
+  }
+
+  pub fn three_bv_per_second(&self) -> Option<f64> {
+    (self.time > 0).then(|| self.three_bv as f64 * 1000.0 / self.time as f64)
+  }
+}
+
+impl<T: Topology> Minesweeper<T> {
+  pub fn stats(&self) -> Stats {
+    let mut stats = self.raw_stats();
+
+    if self.status() == GameStatus::Playing {
+      stats.time = self
+        .started_at()
+        .map_or(0, |started_at| (time::now() - started_at).max(0.0) as u64);
+    }
+
+    stats
+  }
+
+  // Every opening counts as one click, as does every field that is neither a
+  // mine nor next to an opening
+  pub(crate) fn count_three_bv(&self) -> usize {
+    let width = self.width();
+    let is_empty = |pos| !self.is_mine(pos) && self.neighboring_mines(pos) == 0;
+    let mut marked = vec![false; width * self.height()];
+    let mut count = 0;
+
+    for start in self.iter_positions() {
+      if marked[start.1 * width + start.0] || !is_empty(start) {
+        continue;
+      }
+
+      let mut stack = vec![start];
+      marked[start.1 * width + start.0] = true;
+      count += 1;
+
+      while let Some(pos) = stack.pop() {
+        for neighbor in self.iter_neighbors(pos) {
+          let index = neighbor.1 * width + neighbor.0;
+
+          if !marked[index] {
+            marked[index] = true;
+
+            if is_empty(neighbor) {
+              stack.push(neighbor);
+            }
+          }
+        }
+      }
+    }
+
+    count
+      + self
+        .iter_positions()
+        .filter(|&pos| !marked[pos.1 * width + pos.0] && !self.is_mine(pos))
+        .count()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{GameStatus, Minesweeper};
+
+  #[test]
+  fn three_bv() {
+    // . . 1 * 1
+    // . . 1 1 1
+    // 1 1 . . .
+    // * 1 . . .
+    let ms = Minesweeper::with_mines(5, 4, [(3, 0), (0, 3)]);
+
+    // The opening plus the 1 in the top right corner, which does not touch
+    // the opening
+    assert_eq!(ms.stats().three_bv, 2);
+
+    // 1 * 1 . 1 *
+    let ms = Minesweeper::with_mines(6, 1, [(1, 0), (5, 0)]);
+
+    assert_eq!(ms.stats().three_bv, 2);
+  }
+
+  #[test]
+  fn clicks_and_efficiency() {
+    let mut ms = Minesweeper::with_mines(6, 1, [(1, 0), (5, 0)]);
+    ms.open_at((0, 0), 0);
+    ms.toggle_flag_at((1, 0), 500);
+    ms.open_at((0, 0), 700);
+    ms.open_at((3, 0), 2000);
+
+    let stats = ms.stats();
+
+    assert_eq!(ms.status(), GameStatus::Won);
+    assert_eq!(
+      (stats.left_clicks, stats.right_clicks, stats.chord_clicks),
+      (2, 1, 1)
+    );
+    assert_eq!(stats.time, 2000);
+    assert_eq!(stats.efficiency(), Some(0.5));
+    assert_eq!(stats.three_bv_per_second(), Some(1.0));
+  }
+}
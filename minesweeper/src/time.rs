@@ -0,0 +1,16 @@
+#[cfg(not(target_family = "wasm"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Milliseconds since the Unix epoch
+#[cfg(not(target_family = "wasm"))]
+pub fn now() -> f64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|duration| duration.as_secs_f64() * 1000.0)
+    .unwrap_or(0.0)
+}
+
+#[cfg(target_family = "wasm")]
+pub fn now() -> f64 {
+  js_sys::Date::now()
+}
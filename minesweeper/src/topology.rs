@@ -0,0 +1,363 @@
+use crate::minesweeper::Position;
+use std::fmt::Debug;
+
+pub type Offset = (isize, isize);
+
+// Describes which fields count as neighbors of each other
+pub trait Topology: Debug + Clone {
+  fn offsets(&self, pos: Position) -> &[Offset];
+
+  // Whether neighbors wrap around the edges of the board instead of being cut
+  // off
+  fn wraps(&self) -> bool {
+    false
+  }
+
+  // Lets frontends decide how to lay out the board
+  fn name(&self) -> &str;
+
+  // Everything needed to rebuild the topology from saved games and replays,
+  // see `AnyTopology::from_spec`
+  fn spec(&self) -> String {
+    self.name().to_string()
+  }
+}
+
+const SURROUNDING: [Offset; 8] = [
+  (-1, -1),
+  (0, -1),
+  (1, -1),
+  (-1, 0),
+  (1, 0),
+  (-1, 1),
+  (0, 1),
+  (1, 1),
+];
+
+// Hexagonal fields in "odd-r" layout, odd rows are shifted half a field to
+// the right
+const HEX_EVEN_ROW: [Offset; 6] =
+  [(-1, -1), (0, -1), (-1, 0), (1, 0), (-1, 1), (0, 1)];
+const HEX_ODD_ROW: [Offset; 6] =
+  [(0, -1), (1, -1), (-1, 0), (1, 0), (0, 1), (1, 1)];
+
+const KNIGHT_MOVES: [Offset; 8] = [
+  (1, -2),
+  (2, -1),
+  (2, 1),
+  (1, 2),
+  (-1, 2),
+  (-2, 1),
+  (-2, -1),
+  (-1, -2),
+];
+
+// The classic rectangular board with eight neighbors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Grid;
+
+impl Topology for Grid {
+  fn offsets(&self, _: Position) -> &[Offset] {
+    &SURROUNDING
+  }
+
+  fn name(&self) -> &str {
+    "grid"
+  }
+}
+
+// A rectangular board where the edges wrap around
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Torus;
+
+impl Topology for Torus {
+  fn offsets(&self, _: Position) -> &[Offset] {
+    &SURROUNDING
+  }
+
+  fn wraps(&self) -> bool {
+    true
+  }
+
+  fn name(&self) -> &str {
+    "torus"
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Hex;
+
+impl Topology for Hex {
+  fn offsets(&self, (_, y): Position) -> &[Offset] {
+    if y % 2 == 0 {
+      &HEX_EVEN_ROW
+    } else {
+      &HEX_ODD_ROW
+    }
+  }
+
+  fn name(&self) -> &str {
+    "hex"
+  }
+}
+
+// Fields are neighbors if a chess knight can move between them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Knight;
+
+impl Topology for Knight {
+  fn offsets(&self, _: Position) -> &[Offset] {
+    &KNIGHT_MOVES
+  }
+
+  fn name(&self) -> &str {
+    "knight"
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Custom {
+  pub name: String,
+  pub offsets: Vec<Offset>,
+  pub wraps: bool,
+}
+
+impl Topology for Custom {
+  fn offsets(&self, _: Position) -> &[Offset] {
+    &self.offsets
+  }
+
+  fn wraps(&self) -> bool {
+    self.wraps
+  }
+
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  // `custom <name> <wrap|nowrap> <dx>,<dy> ...`
+  fn spec(&self) -> String {
+    let name = self.name.split_whitespace().collect::<Vec<_>>().join("_");
+    let mut spec = format!(
+      "custom {} {}",
+      if name.is_empty() { "custom" } else { &name },
+      if self.wraps { "wrap" } else { "nowrap" }
+    );
+
+    for (dx, dy) in &self.offsets {
+      spec += &format!(" {},{}", dx, dy);
+    }
+
+    spec
+  }
+}
+
+// Any of the topologies above, for when the topology is only known at runtime
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnyTopology {
+  Grid(Grid),
+  Torus(Torus),
+  Hex(Hex),
+  Knight(Knight),
+  Custom(Custom),
+}
+
+impl Default for AnyTopology {
+  fn default() -> Self {
+    AnyTopology::Grid(Grid)
+  }
+}
+
+impl AnyTopology {
+  pub fn from_name(name: &str) -> Option<AnyTopology> {
+    Some(match name {
+      "grid" => AnyTopology::Grid(Grid),
+      "torus" => AnyTopology::Torus(Torus),
+      "hex" => AnyTopology::Hex(Hex),
+      "knight" => AnyTopology::Knight(Knight),
+      _ => return None,
+    })
+  }
+
+  // Parses the result of `Topology::spec`
+  // Zero and repeated offsets are rejected, since they would make a field
+  // its own neighbor or count a neighbor twice
+  pub fn from_spec(spec: &str) -> Option<AnyTopology> {
+    let tokens = spec.split_whitespace().collect::<Vec<_>>();
+
+    match tokens[..] {
+      ["custom", name, wraps, ref offsets @ ..] => {
+        let offsets = offsets
+          .iter()
+          .map(|offset| {
+            let (dx, dy) = offset.split_once(',')?;
+            Some((dx.parse().ok()?, dy.parse().ok()?))
+          })
+          .collect::<Option<Vec<Offset>>>()?;
+
+        if offsets.iter().enumerate().any(|(k, &offset)| {
+          offset == (0, 0) || offsets[..k].contains(&offset)
+        }) {
+          return None;
+        }
+
+        Some(AnyTopology::Custom(Custom {
+          name: name.to_string(),
+          offsets,
+          wraps: match wraps {
+            "wrap" => true,
+            "nowrap" => false,
+            _ => return None,
+          },
+        }))
+      }
+      [name] => AnyTopology::from_name(name),
+      _ => None,
+    }
+  }
+}
+
+impl Topology for AnyTopology {
+  fn offsets(&self, pos: Position) -> &[Offset] {
+    match self {
+      AnyTopology::Grid(topology) => topology.offsets(pos),
+      AnyTopology::Torus(topology) => topology.offsets(pos),
+      AnyTopology::Hex(topology) => topology.offsets(pos),
+      AnyTopology::Knight(topology) => topology.offsets(pos),
+      AnyTopology::Custom(topology) => topology.offsets(pos),
+    }
+  }
+
+  fn wraps(&self) -> bool {
+    match self {
+      AnyTopology::Grid(topology) => topology.wraps(),
+      AnyTopology::Torus(topology) => topology.wraps(),
+      AnyTopology::Hex(topology) => topology.wraps(),
+      AnyTopology::Knight(topology) => topology.wraps(),
+      AnyTopology::Custom(topology) => topology.wraps(),
+    }
+  }
+
+  fn name(&self) -> &str {
+    match self {
+      AnyTopology::Grid(topology) => topology.name(),
+      AnyTopology::Torus(topology) => topology.name(),
+      AnyTopology::Hex(topology) => topology.name(),
+      AnyTopology::Knight(topology) => topology.name(),
+      AnyTopology::Custom(topology) => topology.name(),
+    }
+  }
+
+  fn spec(&self) -> String {
+    match self {
+      AnyTopology::Grid(topology) => topology.spec(),
+      AnyTopology::Torus(topology) => topology.spec(),
+      AnyTopology::Hex(topology) => topology.spec(),
+      AnyTopology::Knight(topology) => topology.spec(),
+      AnyTopology::Custom(topology) => topology.spec(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{AnyTopology, Custom, Grid, Hex, Knight, Topology, Torus};
+  use crate::minesweeper::Minesweeper;
+
+  fn sorted_neighbors<T: super::Topology>(
+    ms: &Minesweeper<T>,
+    pos: (usize, usize),
+  ) -> Vec<(usize, usize)> {
+    let mut neighbors = ms.iter_neighbors(pos).collect::<Vec<_>>();
+    neighbors.sort();
+    neighbors
+  }
+
+  #[test]
+  fn grid_clamps_edges() {
+    let ms = Minesweeper::with_topology_and_mines(Grid, 3, 3, []);
+
+    assert_eq!(sorted_neighbors(&ms, (0, 0)), [(0, 1), (1, 0), (1, 1)]);
+    assert_eq!(ms.iter_neighbors((1, 1)).count(), 8);
+  }
+
+  #[test]
+  fn torus_wraps_edges() {
+    let ms = Minesweeper::with_topology_and_mines(Torus, 4, 4, [(3, 3)]);
+
+    assert_eq!(ms.iter_neighbors((0, 0)).count(), 8);
+    assert_eq!(ms.neighboring_mines((0, 0)), 1);
+
+    // Neighbors must not be counted twice on narrow boards
+    let ms = Minesweeper::with_topology_and_mines(Torus, 2, 1, [(1, 0)]);
+
+    assert_eq!(sorted_neighbors(&ms, (0, 0)), [(1, 0)]);
+    assert_eq!(ms.neighboring_mines((0, 0)), 1);
+  }
+
+  #[test]
+  fn hex_neighbors() {
+    let ms = Minesweeper::with_topology_and_mines(Hex, 4, 4, []);
+
+    assert_eq!(
+      sorted_neighbors(&ms, (1, 1)),
+      [(0, 1), (1, 0), (1, 2), (2, 0), (2, 1), (2, 2)]
+    );
+    assert_eq!(ms.iter_neighbors((1, 2)).count(), 6);
+  }
+
+  #[test]
+  fn knight_and_custom_neighbors() {
+    let ms = Minesweeper::with_topology_and_mines(Knight, 5, 5, [(1, 2)]);
+
+    assert_eq!(ms.iter_neighbors((2, 2)).count(), 8);
+    assert_eq!(sorted_neighbors(&ms, (0, 0)), [(1, 2), (2, 1)]);
+    assert_eq!(ms.neighboring_mines((0, 0)), 1);
+
+    let cross = Custom {
+      name: "cross".to_string(),
+      offsets: vec![(0, -1), (1, 0), (0, 1), (-1, 0)],
+      wraps: false,
+    };
+    let ms = Minesweeper::with_topology_and_mines(cross, 3, 3, []);
+
+    assert_eq!(ms.iter_neighbors((1, 1)).count(), 4);
+
+    for wraps in [false, true] {
+      let far = Custom {
+        name: "far".to_string(),
+        offsets: vec![(0, 0), (isize::MAX, 0), (isize::MIN, 1)],
+        wraps,
+      };
+      let ms = Minesweeper::with_topology_and_mines(far, 3, 3, []);
+
+      assert!(ms.iter_neighbors((2, 1)).all(|pos| pos != (2, 1)));
+    }
+  }
+
+  #[test]
+  fn specs() {
+    let cross = Custom {
+      name: "big cross".to_string(),
+      offsets: vec![(0, -2), (2, 0), (0, 2), (-2, 0)],
+      wraps: true,
+    };
+
+    assert_eq!(cross.spec(), "custom big_cross wrap 0,-2 2,0 0,2 -2,0");
+    assert_eq!(
+      AnyTopology::from_spec(&cross.spec()),
+      Some(AnyTopology::Custom(Custom {
+        name: "big_cross".to_string(),
+        ..cross
+      }))
+    );
+    assert_eq!(
+      AnyTopology::from_spec(&Torus.spec()),
+      Some(AnyTopology::Torus(Torus))
+    );
+    assert_eq!(AnyTopology::from_spec("custom x maybe 1,0"), None);
+    assert_eq!(AnyTopology::from_spec("moebius"), None);
+    assert_eq!(AnyTopology::from_spec("custom x nowrap 1,0 0,0"), None);
+    assert_eq!(AnyTopology::from_spec("custom x nowrap 1,0 1,0"), None);
+  }
+}
@@ -0,0 +1,246 @@
+use crate::{
+  minesweeper::{FlagCycle, GameStatus, Minesweeper, Position},
+  snapshot::{ChangeSet, FieldState},
+  topology::{Grid, Topology},
+};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchMode {
+  // Safe reveals score one point per opened field, opening a mine loses
+  #[default]
+  Reveal,
+  // Opening a mine claims it for a point and gives another turn, the player
+  // with the most mines wins
+  Flags,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Player {
+  One,
+  Two,
+}
+
+impl Player {
+  pub fn other(self) -> Player {
+    match self {
+      Player::One => Player::Two,
+      Player::Two => Player::One,
+    }
+  }
+
+  fn index(self) -> usize {
+    match self {
+      Player::One => 0,
+      Player::Two => 1,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchStatus {
+  Playing,
+  Won(Player),
+  Draw,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Turn {
+  pub player: Player,
+  pub points: usize,
+  pub changes: ChangeSet,
+}
+
+// Two players taking turns on a shared board for hot-seat games. Players can
+// only open covered fields, flags are reserved for claimed mines.
+#[derive(Debug)]
+pub struct Match<T: Topology = Grid> {
+  ms: Minesweeper<T>,
+  mode: MatchMode,
+  current: Player,
+  scores: [usize; 2],
+  turns: usize,
+  claimed: HashMap<Position, Player>,
+  status: MatchStatus,
+}
+
+impl<T: Topology> Match<T> {
+  // Lives and question marks are turned off, player one starts
+  pub fn new(mut ms: Minesweeper<T>, mode: MatchMode) -> Match<T> {
+    ms.set_lives(None);
+    ms.set_flag_cycle(FlagCycle::Flag);
+
+    Match {
+      ms,
+      mode,
+      current: Player::One,
+      scores: [0; 2],
+      turns: 0,
+      claimed: HashMap::new(),
+      status: MatchStatus::Playing,
+    }
+  }
+
+  pub fn game(&self) -> &Minesweeper<T> {
+    &self.ms
+  }
+
+  pub fn mode(&self) -> MatchMode {
+    self.mode
+  }
+
+  pub fn current_player(&self) -> Player {
+    self.current
+  }
+
+  pub fn score(&self, player: Player) -> usize {
+    self.scores[player.index()]
+  }
+
+  // Number of turns played so far
+  pub fn turns(&self) -> usize {
+    self.turns
+  }
+
+  pub fn status(&self) -> MatchStatus {
+    self.status
+  }
+
+  // The player who claimed the mine at the given position in `Flags` mode
+  pub fn owner(&self, pos: Position) -> Option<Player> {
+    self.claimed.get(&pos).copied()
+  }
+
+  // Plays a turn for the current player, returning `None` if the field
+  // cannot be opened
+  pub fn play(&mut self, pos: Position) -> Option<Turn> {
+    if self.status != MatchStatus::Playing
+      || pos.0 >= self.ms.width()
+      || pos.1 >= self.ms.height()
+      || self.ms.is_open(pos)
+      || self.ms.is_flagged(pos)
+    {
+      return None;
+    }
+
+    let player = self.current;
+    let claims_mine = self.mode == MatchMode::Flags
+      && self.ms.mines_placed()
+      && self.ms.is_mine(pos);
+
+    let (changes, points) = if claims_mine {
+      self.claimed.insert(pos, player);
+      (self.ms.toggle_flag(pos), 1)
+    } else {
+      let changes = self.ms.open(pos);
+      let points = match self.mode {
+        MatchMode::Reveal => changes
+          .fields
+          .iter()
+          .filter(|(_, field)| matches!(field, FieldState::Open(_)))
+          .count(),
+        MatchMode::Flags => 0,
+      };
+
+      (changes, points)
+    };
+
+    self.scores[player.index()] += points;
+    self.turns += 1;
+    self.status = self.check_status(player);
+
+    // Claiming a mine gives another turn
+    if !claims_mine {
+      self.current = player.other();
+    }
+
+    Some(Turn {
+      player,
+      points,
+      changes,
+    })
+  }
+
+  fn check_status(&self, player: Player) -> MatchStatus {
+    let mine_count = self.ms.mine_count();
+    let [one, two] = self.scores;
+
+    if self.ms.status() == GameStatus::Lost {
+      return MatchStatus::Won(player.other());
+    }
+
+    let finished = match self.mode {
+      MatchMode::Reveal => self.ms.status() == GameStatus::Won,
+      // Stop once the leader cannot be caught up anymore
+      MatchMode::Flags => {
+        self.ms.status() == GameStatus::Won
+          || one * 2 > mine_count
+          || two * 2 > mine_count
+          || self.claimed.len() == mine_count
+      }
+    };
+
+    match (finished, one.cmp(&two)) {
+      (false, _) => MatchStatus::Playing,
+      (true, std::cmp::Ordering::Greater) => MatchStatus::Won(Player::One),
+      (true, std::cmp::Ordering::Less) => MatchStatus::Won(Player::Two),
+      (true, std::cmp::Ordering::Equal) => MatchStatus::Draw,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Match, MatchMode, MatchStatus, Player};
+  use crate::{snapshot::FieldState, Minesweeper};
+
+  // 1 * 1 . 1 *
+  fn board() -> Minesweeper {
+    Minesweeper::with_mines(6, 1, [(1, 0), (5, 0)])
+  }
+
+  #[test]
+  fn reveal_mode() {
+    let mut game = Match::new(board(), MatchMode::Reveal);
+
+    let turn = game.play((0, 0)).unwrap();
+    assert_eq!((turn.player, turn.points), (Player::One, 1));
+    assert_eq!(game.current_player(), Player::Two);
+    assert_eq!(game.play((0, 0)), None);
+
+    let turn = game.play((3, 0)).unwrap();
+    assert_eq!((turn.player, turn.points), (Player::Two, 3));
+    assert_eq!((game.score(Player::One), game.score(Player::Two)), (1, 3));
+    assert_eq!(game.turns(), 2);
+    assert_eq!(game.status(), MatchStatus::Won(Player::Two));
+    assert_eq!(game.play((1, 0)), None);
+  }
+
+  #[test]
+  fn opening_a_mine_loses() {
+    let mut game = Match::new(board(), MatchMode::Reveal);
+    game.play((3, 0));
+    game.play((1, 0));
+
+    assert_eq!(game.status(), MatchStatus::Won(Player::One));
+  }
+
+  #[test]
+  fn flags_mode() {
+    let mut game = Match::new(board(), MatchMode::Flags);
+
+    assert_eq!(game.play((0, 0)).unwrap().points, 0);
+
+    let turn = game.play((1, 0)).unwrap();
+    assert_eq!((turn.player, turn.points), (Player::Two, 1));
+    assert_eq!(turn.changes.fields, [((1, 0), FieldState::Flagged)]);
+    assert_eq!(game.owner((1, 0)), Some(Player::Two));
+    assert_eq!(game.current_player(), Player::Two);
+    assert_eq!(game.status(), MatchStatus::Playing);
+
+    game.play((5, 0));
+
+    assert_eq!(game.score(Player::Two), 2);
+    assert_eq!(game.status(), MatchStatus::Won(Player::Two));
+  }
+}
@@ -0,0 +1,491 @@
+use crate::{
+  minesweeper::{GameStatus, Minesweeper, Position},
+  render::Emoji,
+  snapshot::{ChangeSet, FieldState},
+  topology::AnyTopology,
+  with_game,
+};
+use js_sys::Function;
+use std::{
+  cell::RefCell,
+  rc::{Rc, Weak},
+};
+use wasm_bindgen::{prelude::*, JsCast, UnwrapThrowExt};
+use web_sys::{
+  window, Element, Event, HtmlElement, KeyboardEvent, PointerEvent,
+};
+
+// How long a touch has to be held to toggle a flag
+const LONG_PRESS_MS: i32 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+  Move(isize, isize),
+  Open,
+  ToggleFlag,
+}
+
+fn key_command(key: &str) -> Option<Command> {
+  match key {
+    "ArrowLeft" => Some(Command::Move(-1, 0)),
+    "ArrowRight" => Some(Command::Move(1, 0)),
+    "ArrowUp" => Some(Command::Move(0, -1)),
+    "ArrowDown" => Some(Command::Move(0, 1)),
+    " " | "Enter" => Some(Command::Open),
+    "f" | "F" => Some(Command::ToggleFlag),
+    _ => None,
+  }
+}
+
+fn move_cursor(
+  (x, y): Position,
+  (dx, dy): (isize, isize),
+  width: usize,
+  height: usize,
+) -> Position {
+  (
+    x.saturating_add_signed(dx).min(width - 1),
+    y.saturating_add_signed(dy).min(height - 1),
+  )
+}
+
+// What screen readers announce for a field
+fn field_label((x, y): Position, field: FieldState) -> String {
+  let state = match field {
+    FieldState::Covered => "covered".to_string(),
+    FieldState::Flagged => "flagged".to_string(),
+    FieldState::Marked => "question mark".to_string(),
+    FieldState::Open(0) => "empty".to_string(),
+    FieldState::Open(1) => "1 mine nearby".to_string(),
+    FieldState::Open(count) => format!("{} mines nearby", count),
+    FieldState::Mine => "mine".to_string(),
+    FieldState::ExplodedMine => "exploded mine".to_string(),
+    FieldState::WrongFlag => "wrong flag".to_string(),
+  };
+
+  format!("Row {}, column {}: {}", y + 1, x + 1, state)
+}
+
+struct ViewState {
+  handle: u32,
+  root: HtmlElement,
+  width: usize,
+  // Field elements in row-major order
+  cells: Vec<HtmlElement>,
+  cursor: Position,
+  on_change: Option<Function>,
+  // Field held down on a touch screen and the timeout that flags it
+  pressed: Option<(Position, i32)>,
+  // Set once a long press flagged a field, so the following click does not
+  // open it
+  long_pressed: bool,
+}
+
+impl ViewState {
+  fn cell(&self, (x, y): Position) -> &HtmlElement {
+    &self.cells[y * self.width + x]
+  }
+
+  fn update_cell(&self, pos: Position, field: FieldState) {
+    let cell = self.cell(pos);
+
+    cell.set_inner_text(Emoji::symbol(field));
+    cell
+      .set_attribute("aria-label", &field_label(pos, field))
+      .unwrap_throw();
+  }
+
+  // Only one field is reachable with tab, the arrow keys move between fields
+  fn set_cursor(&mut self, pos: Position, focus: bool) {
+    self.cell(self.cursor).set_tab_index(-1);
+    self.cursor = pos;
+    self.cell(pos).set_tab_index(0);
+
+    if focus {
+      self.cell(pos).focus().unwrap_throw();
+    }
+  }
+
+  fn render(&mut self) {
+    let document = window().unwrap_throw().document().unwrap_throw();
+    let snapshot = with_game(self.handle, |ms| ms.snapshot());
+    let (width, height) = (snapshot.width, snapshot.height);
+
+    self.root.set_inner_html("");
+    self.root.set_attribute("role", "grid").unwrap_throw();
+    self
+      .root
+      .set_attribute("aria-label", "Minefield")
+      .unwrap_throw();
+    self
+      .root
+      .style()
+      .set_property("display", "inline-grid")
+      .unwrap_throw();
+    self
+      .root
+      .style()
+      .set_property(
+        "grid-template",
+        &format!("repeat({}, auto) / repeat({}, auto)", height, width),
+      )
+      .unwrap_throw();
+
+    self.width = width;
+    self.cells = Vec::with_capacity(width * height);
+    self.cursor = (self.cursor.0.min(width - 1), self.cursor.1.min(height - 1));
+
+    for y in 0..height {
+      // Rows only exist for screen readers, the fields are laid out by the
+      // grid on the root element
+      let row = document.create_element("div").unwrap_throw();
+      row.set_attribute("role", "row").unwrap_throw();
+      row
+        .set_attribute("style", "display: contents")
+        .unwrap_throw();
+
+      for x in 0..width {
+        let cell = document
+          .create_element("div")
+          .unwrap_throw()
+          .dyn_into::<HtmlElement>()
+          .unwrap_throw();
+
+        cell.set_class_name("field");
+        cell.set_attribute("role", "gridcell").unwrap_throw();
+        cell
+          .set_attribute("data-index", &(y * width + x).to_string())
+          .unwrap_throw();
+        cell.set_tab_index(if (x, y) == self.cursor { 0 } else { -1 });
+
+        // Odd rows of hexagonal boards are shifted half a field
+        if snapshot.topology == "hex" && y % 2 == 1 {
+          cell
+            .style()
+            .set_property("transform", "translateX(50%)")
+            .unwrap_throw();
+        }
+
+        row.append_child(&cell).unwrap_throw();
+        self.cells.push(cell);
+        self.update_cell((x, y), snapshot.get((x, y)));
+      }
+
+      self.root.append_child(&row).unwrap_throw();
+    }
+  }
+
+  // Returns the position of the field an event happened on
+  fn event_position(&self, evt: &Event) -> Option<Position> {
+    let index = evt
+      .target()?
+      .dyn_into::<Element>()
+      .ok()?
+      .closest("[data-index]")
+      .ok()??
+      .get_attribute("data-index")?
+      .parse::<usize>()
+      .ok()?;
+
+    (index < self.cells.len()).then(|| (index % self.width, index / self.width))
+  }
+}
+
+type Listener = Closure<dyn FnMut(Event)>;
+
+// Renders a game into an element and handles mouse, touch and keyboard input
+// on it. Arrow keys move the cursor, space or enter opens a field and f
+// toggles a flag. `onChange` is called with the new status after every
+// action.
+#[wasm_bindgen]
+pub struct BoardView {
+  state: Rc<RefCell<ViewState>>,
+  listeners: Vec<(&'static str, Listener)>,
+  _on_long_press: Rc<Closure<dyn FnMut()>>,
+}
+
+// Performs an action and updates the fields that changed. The state must not
+// be borrowed while calling `onChange`, which may call back into the view.
+fn perform(
+  state: &RefCell<ViewState>,
+  pos: Position,
+  action: fn(&mut Minesweeper<AnyTopology>, Position) -> ChangeSet,
+  focus: bool,
+) {
+  let (status, on_change) = {
+    let mut state = state.borrow_mut();
+    let changes = with_game(state.handle, |ms| action(ms, pos));
+
+    for &(pos, field) in &changes.fields {
+      state.update_cell(pos, field);
+    }
+
+    state.set_cursor(pos, focus);
+    (changes.status, state.on_change.clone())
+  };
+
+  notify(on_change, status);
+}
+
+fn notify(on_change: Option<Function>, status: GameStatus) {
+  if let Some(on_change) = on_change {
+    on_change
+      .call1(&JsValue::NULL, &status.name().into())
+      .unwrap_throw();
+  }
+}
+
+fn listener(
+  state: &Rc<RefCell<ViewState>>,
+  mut f: impl FnMut(&Rc<RefCell<ViewState>>, Event) + 'static,
+) -> Listener {
+  let state = Rc::downgrade(state);
+
+  Closure::wrap(Box::new(move |evt: Event| {
+    if let Some(state) = state.upgrade() {
+      f(&state, evt);
+    }
+  }) as Box<dyn FnMut(Event)>)
+}
+
+fn cancel_long_press(state: &mut ViewState) -> bool {
+  match state.pressed.take() {
+    Some((_, timeout)) => {
+      window().unwrap_throw().clear_timeout_with_handle(timeout);
+      true
+    }
+    None => false,
+  }
+}
+
+#[wasm_bindgen]
+impl BoardView {
+  #[wasm_bindgen(constructor)]
+  pub fn new(
+    root: HtmlElement,
+    handle: u32,
+    on_change: Option<Function>,
+  ) -> BoardView {
+    let state = Rc::new(RefCell::new(ViewState {
+      handle,
+      root: root.clone(),
+      width: 0,
+      cells: vec![],
+      cursor: with_game(handle, |ms| (ms.width() / 2, ms.height() / 2)),
+      on_change,
+      pressed: None,
+      long_pressed: false,
+    }));
+
+    state.borrow_mut().render();
+
+    let on_long_press = {
+      let state: Weak<RefCell<ViewState>> = Rc::downgrade(&state);
+
+      Rc::new(Closure::wrap(Box::new(move || {
+        let Some(state) = state.upgrade() else {
+          return;
+        };
+        let pressed = state.borrow_mut().pressed.take();
+
+        if let Some((pos, _)) = pressed {
+          state.borrow_mut().long_pressed = true;
+          perform(&state, pos, Minesweeper::toggle_flag, false);
+        }
+      }) as Box<dyn FnMut()>))
+    };
+
+    let long_press_callback = on_long_press.clone();
+    let listeners = vec![
+      (
+        "click",
+        listener(&state, |state, evt| {
+          let pos = {
+            let mut state = state.borrow_mut();
+
+            if std::mem::take(&mut state.long_pressed) {
+              return;
+            }
+
+            state.event_position(&evt)
+          };
+
+          if let Some(pos) = pos {
+            evt.prevent_default();
+            perform(state, pos, Minesweeper::open, false);
+          }
+        }),
+      ),
+      (
+        "contextmenu",
+        listener(&state, |state, evt| {
+          evt.prevent_default();
+
+          let pos = {
+            let mut state = state.borrow_mut();
+
+            if state.long_pressed {
+              return;
+            }
+
+            // Some browsers open the context menu on a long press before the
+            // timeout fires
+            if cancel_long_press(&mut state) {
+              state.long_pressed = true;
+            }
+
+            state.event_position(&evt)
+          };
+
+          if let Some(pos) = pos {
+            perform(state, pos, Minesweeper::toggle_flag, false);
+          }
+        }),
+      ),
+      (
+        "pointerdown",
+        listener(&state, move |state, evt| {
+          let is_mouse = evt
+            .dyn_ref::<PointerEvent>()
+            .is_none_or(|evt| evt.pointer_type() == "mouse");
+          let mut state = state.borrow_mut();
+
+          cancel_long_press(&mut state);
+          state.long_pressed = false;
+
+          if let (false, Some(pos)) = (is_mouse, state.event_position(&evt)) {
+            let timeout = window()
+              .unwrap_throw()
+              .set_timeout_with_callback_and_timeout_and_arguments_0(
+                (*long_press_callback).as_ref().unchecked_ref(),
+                LONG_PRESS_MS,
+              )
+              .unwrap_throw();
+
+            state.pressed = Some((pos, timeout));
+          }
+        }),
+      ),
+      (
+        "pointerup",
+        listener(&state, |state, _| {
+          cancel_long_press(&mut state.borrow_mut());
+        }),
+      ),
+      (
+        "pointercancel",
+        listener(&state, |state, _| {
+          cancel_long_press(&mut state.borrow_mut());
+        }),
+      ),
+      (
+        "keydown",
+        listener(&state, |state, evt| {
+          let command = evt
+            .dyn_ref::<KeyboardEvent>()
+            .and_then(|evt| key_command(&evt.key()));
+          let Some(command) = command else {
+            return;
+          };
+
+          evt.prevent_default();
+
+          let cursor = state.borrow().cursor;
+
+          match command {
+            Command::Move(dx, dy) => {
+              let mut state = state.borrow_mut();
+              let height = state.cells.len() / state.width;
+              let pos = move_cursor(cursor, (dx, dy), state.width, height);
+
+              state.set_cursor(pos, true);
+            }
+            Command::Open => perform(state, cursor, Minesweeper::open, true),
+            Command::ToggleFlag => {
+              perform(state, cursor, Minesweeper::toggle_flag, true)
+            }
+          }
+        }),
+      ),
+    ];
+
+    for (name, listener) in &listeners {
+      root
+        .add_event_listener_with_callback(
+          name,
+          listener.as_ref().unchecked_ref(),
+        )
+        .unwrap_throw();
+    }
+
+    BoardView {
+      state,
+      listeners,
+      _on_long_press: on_long_press,
+    }
+  }
+
+  // Rebuilds all fields, for example after undo or redo
+  pub fn render(&self) {
+    self.state.borrow_mut().render();
+  }
+
+  pub fn focus(&self) {
+    let mut state = self.state.borrow_mut();
+    let cursor = state.cursor;
+
+    state.set_cursor(cursor, true);
+  }
+}
+
+impl Drop for BoardView {
+  fn drop(&mut self) {
+    let mut state = self.state.borrow_mut();
+
+    cancel_long_press(&mut state);
+
+    for (name, listener) in &self.listeners {
+      let _ = state.root.remove_event_listener_with_callback(
+        name,
+        listener.as_ref().unchecked_ref(),
+      );
+    }
+
+    state.root.set_inner_html("");
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{field_label, key_command, move_cursor, Command};
+  use crate::snapshot::FieldState;
+
+  #[test]
+  fn keys() {
+    assert_eq!(key_command("ArrowUp"), Some(Command::Move(0, -1)));
+    assert_eq!(key_command(" "), Some(Command::Open));
+    assert_eq!(key_command("Enter"), Some(Command::Open));
+    assert_eq!(key_command("f"), Some(Command::ToggleFlag));
+    assert_eq!(key_command("x"), None);
+
+    assert_eq!(move_cursor((0, 0), (-1, 0), 9, 9), (0, 0));
+    assert_eq!(move_cursor((8, 4), (1, 0), 9, 9), (8, 4));
+    assert_eq!(move_cursor((3, 4), (0, 1), 9, 9), (3, 5));
+  }
+
+  #[test]
+  fn labels() {
+    assert_eq!(
+      field_label((0, 2), FieldState::Covered),
+      "Row 3, column 1: covered"
+    );
+    assert_eq!(
+      field_label((4, 0), FieldState::Open(1)),
+      "Row 1, column 5: 1 mine nearby"
+    );
+    assert_eq!(
+      field_label((4, 0), FieldState::Open(3)),
+      "Row 1, column 5: 3 mines nearby"
+    );
+  }
+}